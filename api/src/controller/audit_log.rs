@@ -0,0 +1,224 @@
+use crate::{
+    schema::audit_log::{self, dsl::*},
+    websocket::DataType,
+    Database,
+};
+use derive_more::Display;
+use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use serde::Serialize;
+use serde_json::{map::Map, Value as Json};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single action performed on an entity, along with who performed it.
+///
+/// For updates, only the fields that changed are present in `before` and `after`.
+/// Creations have no `before`, and deletions have no `after`.
+/// `actor_user_id` is `None` when the action was performed by the API itself,
+/// or if the user has since been deleted.
+#[derive(Debug, Clone, Serialize, Queryable)]
+pub struct AuditLog {
+    pub id: i32,
+    pub actor_user_id: Option<i32>,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub thread_id: Option<i32>,
+    pub action: String,
+    pub before: Option<Json>,
+    pub after: Option<Json>,
+    pub created_at_utc: i64,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "audit_log"]
+struct InsertAuditLog {
+    actor_user_id:  Option<i32>,
+    entity_type:    String,
+    entity_id:      i32,
+    thread_id:      Option<i32>,
+    action:         String,
+    before:         Option<Json>,
+    after:          Option<Json>,
+    created_at_utc: i64,
+}
+
+/// What was done to an entity.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    #[display(fmt = "create")]
+    Create,
+    #[display(fmt = "update")]
+    Update,
+    #[display(fmt = "delete")]
+    Delete,
+    #[display(fmt = "undelete")]
+    Undelete,
+    #[display(fmt = "lock")]
+    Lock,
+    #[display(fmt = "approve")]
+    Approve,
+    #[display(fmt = "sticky")]
+    Sticky,
+    #[display(fmt = "unsticky")]
+    Unsticky,
+}
+
+/// The entity an action was performed on,
+/// along with the `Thread` it belongs to, if any.
+#[derive(Debug)]
+pub struct AuditEntity {
+    pub data_type: DataType,
+    pub id:        i32,
+    pub thread_id: Option<i32>,
+}
+
+/// An entity whose changes are recorded in the `AuditLog`.
+pub trait Audited: Serialize {
+    fn audit_entity(&self) -> AuditEntity;
+}
+
+/// Restrict which entries are returned from `AuditLog::find_filtered`.
+/// `since` and `until` are inclusive.
+#[derive(Debug, Default)]
+pub struct AuditLogFilter {
+    pub thread_id:     Option<i32>,
+    pub actor_user_id: Option<i32>,
+    pub since:         Option<i64>,
+    pub until:         Option<i64>,
+    pub limit:         i64,
+}
+
+/// Reduce two objects to only the fields that differ between them.
+/// Values that aren't objects are returned unchanged.
+#[inline]
+fn diff(old: Json, new: Json) -> (Json, Json) {
+    match (old, new) {
+        (Json::Object(old), Json::Object(mut new)) => {
+            let mut changed_old = Map::new();
+            let mut changed_new = Map::new();
+
+            for (key, old_value) in old {
+                match new.remove(&key) {
+                    Some(ref new_value) if *new_value == old_value => {}
+                    Some(new_value) => {
+                        changed_old.insert(key.clone(), old_value);
+                        changed_new.insert(key, new_value);
+                    }
+                    None => {
+                        changed_old.insert(key, old_value);
+                    }
+                }
+            }
+            changed_new.extend(new);
+
+            (Json::Object(changed_old), Json::Object(changed_new))
+        }
+        (old, new) => (old, new),
+    }
+}
+
+impl AuditLog {
+    /// Find the most recent entries matching the filter, newest first.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// as entries are never modified.
+    #[inline]
+    pub fn find_filtered(conn: &Database, filter: &AuditLogFilter) -> QueryResult<Vec<Self>> {
+        let mut query = audit_log.into_boxed();
+
+        if let Some(for_thread_id) = filter.thread_id {
+            query = query.filter(thread_id.eq(for_thread_id));
+        }
+        if let Some(for_user_id) = filter.actor_user_id {
+            query = query.filter(actor_user_id.eq(for_user_id));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(created_at_utc.ge(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(created_at_utc.le(until));
+        }
+
+        query
+            .order((created_at_utc.desc(), id.desc()))
+            .limit(filter.limit)
+            .load(conn)
+    }
+
+    /// Record an action performed on an entity.
+    ///
+    /// When both `old` and `new` are provided,
+    /// only the fields that differ between them are stored.
+    /// If neither is provided, nothing is recorded.
+    #[inline]
+    pub fn record<T: Audited>(
+        conn: &Database,
+        actor_id: Option<i32>,
+        audit_action: AuditAction,
+        old: Option<&T>,
+        new: Option<&T>,
+    ) -> QueryResult<()> {
+        let entity = match new.or(old) {
+            Some(value) => value.audit_entity(),
+            None => return Ok(()),
+        };
+
+        let to_json = |value: &T| serde_json::to_value(value).unwrap_or(Json::Null);
+        let (old, new) = match (old.map(to_json), new.map(to_json)) {
+            (Some(old), Some(new)) => {
+                let (old, new) = diff(old, new);
+                (Some(old), Some(new))
+            }
+            values => values,
+        };
+
+        Self::insert(conn, actor_id, entity, audit_action, old, new)
+    }
+
+    /// Record an action that doesn't change any stored data,
+    /// such as approving a thread on Reddit.
+    #[inline]
+    pub fn record_action<T: Audited>(
+        conn: &Database,
+        actor_id: Option<i32>,
+        audit_action: AuditAction,
+        value: &T,
+    ) -> QueryResult<()> {
+        Self::insert(
+            conn,
+            actor_id,
+            value.audit_entity(),
+            audit_action,
+            None,
+            None,
+        )
+    }
+
+    #[inline]
+    fn insert(
+        conn: &Database,
+        actor_id: Option<i32>,
+        entity: AuditEntity,
+        audit_action: AuditAction,
+        old: Option<Json>,
+        new: Option<Json>,
+    ) -> QueryResult<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        diesel::insert_into(audit_log)
+            .values(&InsertAuditLog {
+                actor_user_id:  actor_id,
+                entity_type:    entity.data_type.to_string(),
+                entity_id:      entity.id,
+                thread_id:      entity.thread_id,
+                action:         audit_action.to_string(),
+                before:         old,
+                after:          new,
+                created_at_utc: now,
+            })
+            .execute(conn)
+            .map(|_| ())
+    }
+}
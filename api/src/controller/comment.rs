@@ -0,0 +1,443 @@
+use super::{
+    overflow,
+    transaction,
+    AuditAction,
+    AuditEntity,
+    AuditLog,
+    Audited,
+    Deferred,
+    Section,
+    Thread,
+    ToMarkdown,
+    User,
+    COMMENT_CACHE_SIZE,
+};
+use crate::{
+    schema::comment::{self, dsl::*},
+    websocket::*,
+    Database,
+};
+use enceladus_macros::generate_structs;
+use lazy_static::lazy_static;
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use reddit::Distinguish;
+use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use serde::Deserialize;
+use serde_json::json;
+use std::{error::Error, fmt::Write};
+
+lazy_static! {
+    /// A global cache, containing a mapping of IDs to their respective `Comment`.
+    ///
+    /// The cache is protected by a `Mutex`,
+    /// ensuring there is only ever at most one writer at a time.
+    /// Note that even when reading,
+    /// there must be a lock on mutability,
+    /// as the `LruCache` must be able to update itself.
+    ///
+    /// To read from the cache,
+    /// you'll want to call `CACHE.lock()` before performing normal operations.
+    static ref CACHE: Mutex<LruCache<i32, Comment>> = Mutex::new(LruCache::new(COMMENT_CACHE_SIZE));
+}
+
+// `reddit_id` and `markdown` are set once the comment has been pushed to Reddit,
+// so they are declared as `auto`.
+generate_structs! {
+    Comment("comment") {
+        auto id: i32,
+        readonly in_thread_id: i32,
+        auto reddit_id: Option<String>,
+        sections_id: Vec<i32> = vec![],
+        is_stickied: bool = false,
+        readonly is_continuation: bool = false,
+        auto markdown: String,
+    }
+}
+
+// Continuation comments are managed automatically,
+// so users can't create them directly.
+// Use an `ExternalInsertComment` wherever user input is expected.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalInsertComment {
+    pub in_thread_id: i32,
+    pub sections_id:  Option<Vec<i32>>,
+    pub is_stickied:  Option<bool>,
+}
+
+impl Comment {
+    /// Find all `Comment`s in the database.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// so as to avoid storing values rarely accessed.
+    #[inline]
+    pub fn find_all(conn: &Database) -> QueryResult<Vec<Self>> {
+        comment.load(conn)
+    }
+
+    /// Find all `Comment`s belonging to a given `Thread`.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_by_thread(conn: &Database, thread_id: i32) -> QueryResult<Vec<Self>> {
+        comment
+            .filter(in_thread_id.eq(thread_id))
+            .order(id)
+            .load(conn)
+    }
+
+    /// Find a given `Comment` by its ID.
+    ///
+    /// Internally uses a cache to limit database accesses.
+    #[inline]
+    pub fn find_id(conn: &Database, comment_id: i32) -> QueryResult<Self> {
+        let mut cache = CACHE.lock();
+        if cache.contains_key(&comment_id) {
+            Ok(cache.get_mut(&comment_id).unwrap().clone())
+        } else {
+            let result: Self = comment.find(comment_id).first(conn)?;
+            cache.insert(comment_id, result.clone());
+            Ok(result)
+        }
+    }
+
+    /// Create a `Comment` given the data.
+    ///
+    /// The comment is not posted to Reddit until its thread is next updated.
+    /// The creation is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn create(
+        conn: &Database,
+        data: &ExternalInsertComment,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Self> {
+        Self::insert(
+            conn,
+            &InsertComment {
+                in_thread_id:    data.in_thread_id,
+                sections_id:     data.sections_id.clone().unwrap_or_default(),
+                is_stickied:     data.is_stickied.unwrap_or(false),
+                is_continuation: false,
+            },
+            actor_id,
+        )
+    }
+
+    /// Insert a `Comment` into the database.
+    ///
+    /// The inserted row is returned.
+    /// Writing it to cache and notifying clients
+    /// are deferred until the transaction has been committed.
+    #[inline]
+    fn insert(conn: &Database, data: &InsertComment, actor_id: Option<i32>) -> QueryResult<Self> {
+        transaction(conn, |deferred| {
+            let result: Self = diesel::insert_into(comment).values(data).get_result(conn)?;
+            AuditLog::record(conn, actor_id, AuditAction::Create, None, Some(&result))?;
+
+            let cached = result.clone();
+            deferred.push(move || {
+                CACHE.lock().insert(cached.id, cached);
+            });
+            deferred.send(Message {
+                room:      Room::Thread(result.in_thread_id),
+                action:    Action::Create,
+                data_type: DataType::Comment,
+                data:      &result,
+            });
+
+            Ok(result)
+        })
+    }
+
+    /// Update a `Comment` given an ID and the data to update.
+    ///
+    /// The entry is updated in the database, added to cache, and returned.
+    /// The change is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn update(
+        conn: &Database,
+        comment_id: i32,
+        data: &UpdateComment,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Self> {
+        transaction(conn, |deferred| {
+            Self::update_deferred(conn, comment_id, data, actor_id, deferred)
+        })
+    }
+
+    /// Update a `Comment` as part of a larger transaction.
+    ///
+    /// Writing the entry to cache and notifying clients
+    /// are deferred until the transaction has been committed.
+    #[inline]
+    fn update_deferred(
+        conn: &Database,
+        comment_id: i32,
+        data: &UpdateComment,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<Self> {
+        let old = Self::find_id(conn, comment_id)?;
+        let result: Self = diesel::update(comment)
+            .filter(id.eq(comment_id))
+            .set(data)
+            .get_result(conn)?;
+        AuditLog::record(
+            conn,
+            actor_id,
+            AuditAction::Update,
+            Some(&old),
+            Some(&result),
+        )?;
+
+        let cached = result.clone();
+        deferred.push(move || {
+            CACHE.lock().insert(cached.id, cached);
+        });
+        deferred.send(Message {
+            room:      Room::Thread(result.in_thread_id),
+            action:    Action::Update,
+            data_type: DataType::Comment,
+            data:      &Update::new(comment_id, data),
+        });
+
+        Ok(result)
+    }
+
+    /// Delete a `Comment` given its ID.
+    ///
+    /// Returns the number of rows deleted (should be `1`),
+    /// removing the entry from cache once the transaction has been committed.
+    /// Does _not_ delete the comment on Reddit; use `delete_on_reddit` for that.
+    /// The deletion is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn delete(conn: &Database, comment_id: i32, actor_id: Option<i32>) -> QueryResult<usize> {
+        transaction(conn, |deferred| {
+            let old = Self::find_id(conn, comment_id)?;
+            AuditLog::record(conn, actor_id, AuditAction::Delete, Some(&old), None)?;
+            let result = diesel::delete(comment)
+                .filter(id.eq(comment_id))
+                .execute(conn)?;
+
+            deferred.push(move || {
+                CACHE.lock().remove(&comment_id);
+            });
+            deferred.send(Message {
+                room:      Room::Thread(old.in_thread_id),
+                action:    Action::Delete,
+                data_type: DataType::Comment,
+                data:      &json!({ "id": comment_id }),
+            });
+
+            Ok(result)
+        })
+    }
+
+    /// Remove any references to a `Section` from the `Comment`s in its thread,
+    /// as part of deleting the section.
+    #[inline]
+    pub fn remove_section(
+        conn: &Database,
+        thread_id: i32,
+        section_id: i32,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<()> {
+        for mut value in Self::find_by_thread(conn, thread_id)? {
+            if value.sections_id.contains(&section_id) {
+                value.sections_id.retain(|&cur_id| cur_id != section_id);
+                Self::update_deferred(
+                    conn,
+                    value.id,
+                    &UpdateComment {
+                        sections_id: value.sections_id.into(),
+                        ..Default::default()
+                    },
+                    actor_id,
+                    deferred,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set which `Section`s are spilled into the thread's continuation comment,
+    /// creating the comment if necessary.
+    ///
+    /// If no sections are provided, any existing continuation comment is deleted,
+    /// both on Reddit and in the database.
+    /// As this is performed automatically, changes are not attributed to any user.
+    #[inline]
+    pub fn set_continuation(
+        conn: &Database,
+        thread_id: i32,
+        continued_sections_id: Vec<i32>,
+    ) -> Result<(), Box<dyn Error>> {
+        let continuation = Self::find_by_thread(conn, thread_id)?
+            .into_iter()
+            .find(|value| value.is_continuation);
+
+        match continuation {
+            Some(continuation) if continued_sections_id.is_empty() => {
+                continuation.delete_on_reddit(conn)?;
+                Self::delete(conn, continuation.id, None)?;
+            }
+            Some(continuation) => {
+                if continuation.sections_id != continued_sections_id {
+                    Self::update(
+                        conn,
+                        continuation.id,
+                        &UpdateComment {
+                            sections_id: continued_sections_id.into(),
+                            ..Default::default()
+                        },
+                        None,
+                    )?;
+                }
+            }
+            None if continued_sections_id.is_empty() => {}
+            None => {
+                // The comment is posted by the thread's author,
+                // who can only sticky it if they moderate the subreddit.
+                let thread = Thread::find_id(conn, thread_id)?;
+                let stickied = User::find_id(conn, thread.created_by_user_id)?
+                    .is_moderator_of(conn, thread.subreddit.as_ref().map(String::as_str));
+
+                Self::insert(
+                    conn,
+                    &InsertComment {
+                        in_thread_id:    thread_id,
+                        sections_id:     continued_sections_id,
+                        is_stickied:     stickied,
+                        is_continuation: true,
+                    },
+                    None,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Post the `Comment` to Reddit as a reply to the thread,
+    /// or edit it if it has already been posted.
+    ///
+    /// Nothing is sent if the markdown is identical to what was last pushed.
+    #[inline]
+    pub fn update_on_reddit(
+        &self,
+        conn: &Database,
+        user: &mut reddit::User<'_>,
+        thread_post_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let md = self.to_markdown(conn)?;
+
+        let (posted_id, is_new) = match &self.reddit_id {
+            Some(_) if md == self.markdown => return Ok(()),
+            Some(posted_id) => {
+                user.edit_comment(&format!("t1_{}", posted_id), &md)?;
+                (posted_id.clone(), false)
+            }
+            None => (
+                user.submit_comment(&format!("t3_{}", thread_post_id), &md)?,
+                true,
+            ),
+        };
+
+        // Store the ID before doing anything else,
+        // so that a failure below doesn't result in the comment being posted again.
+        let result: Self = diesel::update(comment)
+            .filter(id.eq(self.id))
+            .set((reddit_id.eq(&posted_id), markdown.eq(&md)))
+            .get_result(conn)?;
+        CACHE.lock().insert(result.id, result);
+
+        if is_new && self.is_stickied {
+            user.distinguish(&format!("t1_{}", posted_id), Distinguish::Moderator, true)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sticky or unsticky the `Comment` on Reddit.
+    /// Has no effect if the comment has yet to be posted,
+    /// as the stickied state is applied when it is.
+    #[inline]
+    pub fn set_sticky_on_reddit(&self, conn: &Database, state: bool) -> Result<(), Box<dyn Error>> {
+        let posted_id = match &self.reddit_id {
+            Some(posted_id) => posted_id,
+            None => return Ok(()),
+        };
+
+        let author_id = Thread::find_id(conn, self.in_thread_id)?.created_by_user_id;
+        let mut user: reddit::User<'_> = User::find_id(conn, author_id)?.into();
+
+        user.distinguish(
+            &format!("t1_{}", posted_id),
+            match state {
+                true => Distinguish::Moderator,
+                false => Distinguish::None,
+            },
+            state,
+        )?;
+
+        User::update_access_token_if_necessary(conn, author_id, &mut user)?;
+
+        Ok(())
+    }
+
+    /// Delete the `Comment` on Reddit.
+    /// Has no effect if the comment has yet to be posted.
+    #[inline]
+    pub fn delete_on_reddit(&self, conn: &Database) -> Result<(), Box<dyn Error>> {
+        let posted_id = match &self.reddit_id {
+            Some(posted_id) => posted_id,
+            None => return Ok(()),
+        };
+
+        let author_id = Thread::find_id(conn, self.in_thread_id)?.created_by_user_id;
+        let mut user: reddit::User<'_> = User::find_id(conn, author_id)?.into();
+
+        user.delete(&format!("t1_{}", posted_id))?;
+
+        User::update_access_token_if_necessary(conn, author_id, &mut user)?;
+
+        Ok(())
+    }
+}
+
+impl Audited for Comment {
+    #[inline]
+    fn audit_entity(&self) -> AuditEntity {
+        AuditEntity {
+            data_type: DataType::Comment,
+            id:        self.id,
+            thread_id: Some(self.in_thread_id),
+        }
+    }
+}
+
+impl ToMarkdown for Comment {
+    /// Convert the `Comment` object to valid markdown.
+    /// The resulting string is intended for consumption by Reddit,
+    /// and is cut off at Reddit's limit on the length of a comment.
+    #[inline]
+    fn to_markdown(&self, conn: &Database) -> Result<String, Box<dyn Error>> {
+        let mut md = String::new();
+
+        for &section_id in self.sections_id.iter() {
+            writeln!(
+                &mut md,
+                "{}\n",
+                Section::find_id(conn, section_id)?.to_markdown(conn)?
+            )?;
+        }
+
+        match overflow::fits(&md, reddit::MAX_COMMENT_LENGTH) {
+            true => Ok(md),
+            false => Ok(overflow::truncate(&md, reddit::MAX_COMMENT_LENGTH)),
+        }
+    }
+}
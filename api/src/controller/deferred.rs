@@ -0,0 +1,64 @@
+use crate::{websocket::Message, Database};
+use rocket_contrib::databases::diesel::{Connection, QueryResult};
+use serde::Serialize;
+use serde_json::value::Value as Json;
+
+/// Side effects, such as cache writes and WebSocket messages,
+/// that must not be observed until the transaction producing them has been committed.
+///
+/// Effects are performed in the order they were added.
+#[derive(Default)]
+pub struct Deferred(Vec<Box<dyn FnOnce()>>);
+
+impl Deferred {
+    /// Perform the effect once the transaction has been committed.
+    #[inline]
+    pub fn push(&mut self, effect: impl FnOnce() + 'static) {
+        self.0.push(Box::new(effect));
+    }
+
+    /// Send the `Message` once the transaction has been committed.
+    ///
+    /// The data is serialized immediately,
+    /// so it reflects the state at the time of the call.
+    #[inline]
+    pub fn send<T: Serialize>(&mut self, message: Message<'_, T>) {
+        let Message {
+            room,
+            action,
+            data_type,
+            data,
+        } = message;
+        let data = serde_json::to_value(data).unwrap_or(Json::Null);
+
+        self.push(move || {
+            let _ = Message {
+                room,
+                action,
+                data_type,
+                data: &data,
+            }
+            .send();
+        });
+    }
+}
+
+/// Run `f` within a database transaction,
+/// performing the effects it defers only if the transaction is committed.
+///
+/// If `f` returns an error, the transaction is rolled back
+/// and all deferred effects are discarded.
+#[inline]
+pub fn transaction<T>(
+    conn: &Database,
+    f: impl FnOnce(&mut Deferred) -> QueryResult<T>,
+) -> QueryResult<T> {
+    let mut deferred = Deferred::default();
+    let result = conn.transaction(|| f(&mut deferred))?;
+
+    for effect in deferred.0 {
+        effect();
+    }
+
+    Ok(result)
+}
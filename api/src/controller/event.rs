@@ -1,12 +1,39 @@
-use super::{Thread, ToMarkdown, UpdateThread, EVENT_CACHE_SIZE};
+use super::{
+    may_set_lock,
+    transaction,
+    AuditAction,
+    AuditEntity,
+    AuditLog,
+    Audited,
+    Deferred,
+    EventRevision,
+    Rejection,
+    Thread,
+    ToMarkdown,
+    UpdateThread,
+    User,
+    EVENT_CACHE_SIZE,
+    LOCK_DURATION_SECONDS,
+};
 use crate::{schema::event, websocket::*, Database};
 use enceladus_macros::generate_structs;
 use lazy_static::lazy_static;
 use lru_cache::LruCache;
 use parking_lot::Mutex;
-use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use rocket_contrib::databases::diesel::{
+    ExpressionMethods,
+    OptionalExtension,
+    QueryDsl,
+    QueryResult,
+    RunQueryDsl,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::{error::Error, fmt::Write};
+use std::{
+    error::Error,
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 lazy_static! {
     /// A global cache, containing a mapping of IDs to their respective `Event`.
@@ -23,17 +50,57 @@ lazy_static! {
     static ref CACHE: Mutex<LruCache<i32, Event>> = Mutex::new(LruCache::new(EVENT_CACHE_SIZE));
 }
 
+// Fields relating to the lock are handled by the `LockEvent` struct,
+// and fields relating to deletion by `delete` and `undelete`.
 generate_structs! {
     Event("event") {
         auto id: i32,
         posted: bool = false,
         readonly in_thread_id: i32,
         cols: serde_json::Value,
+        auto deleted_at_utc: Option<i64>,
+        auto deleted_position: Option<i32>,
+        auto version: i32,
+        auto lock_held_by_user_id: Option<i32>,
+        auto lock_assigned_at_utc: i64,
+        auto lock_expires_at_utc: Option<i64>,
+    }
+}
+
+/// Only these fields may be externally present when setting an event's lock.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalLockEvent {
+    pub lock_held_by_user_id: Option<i32>,
+}
+
+/// Only these fields may be internally present when setting an event's lock.
+///
+/// A `None` lock holder is written as `NULL`, releasing the lock.
+#[derive(Serialize, AsChangeset, Debug)]
+#[table_name = "event"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct LockEvent {
+    pub lock_held_by_user_id: Option<i32>,
+    pub lock_assigned_at_utc: i64,
+    pub lock_expires_at_utc:  Option<i64>,
+}
+
+impl LockEvent {
+    /// Assign the lock to the provided user (or nobody) at the given time,
+    /// expiring `LOCK_DURATION_SECONDS` later if held.
+    #[inline]
+    pub fn new(user_id: Option<i32>, now: i64) -> Self {
+        LockEvent {
+            lock_held_by_user_id: user_id,
+            lock_assigned_at_utc: now,
+            lock_expires_at_utc:  user_id.map(|_| now + LOCK_DURATION_SECONDS),
+        }
     }
 }
 
 impl Event {
-    /// Find all `Event`s in the database.
+    /// Find all `Event`s in the database that have not been deleted.
     ///
     /// Does _not_ use cache (reading or writing),
     /// so as to avoid storing values rarely accessed.
@@ -41,10 +108,21 @@ impl Event {
     pub fn find_all(conn: &Database) -> QueryResult<Vec<Self>> {
         use crate::schema::event::dsl::*;
 
+        event.filter(deleted_at_utc.is_null()).load(conn)
+    }
+
+    /// Find all `Event`s in the database, including those that have been deleted.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_all_including_deleted(conn: &Database) -> QueryResult<Vec<Self>> {
+        use crate::schema::event::dsl::*;
+
         event.load(conn)
     }
 
-    /// Find a given `Event` by its ID.
+    /// Find a given `Event` by its ID,
+    /// returning `NotFound` if it has been deleted.
     ///
     /// Internally uses a cache to limit database accesses.
     #[inline]
@@ -55,97 +133,608 @@ impl Event {
         if cache.contains_key(&event_id) {
             Ok(cache.get_mut(&event_id).unwrap().clone())
         } else {
-            let result: Self = event.find(event_id).first(conn)?;
+            let result: Self = event
+                .find(event_id)
+                .filter(deleted_at_utc.is_null())
+                .first(conn)?;
             cache.insert(event_id, result.clone());
             Ok(result)
         }
     }
 
-    /// Create an `Event` given the data.
+    /// Find a given `Event` by its ID, even if it has been deleted.
     ///
-    /// The inserted row is added to the global cache and returned.
+    /// Does _not_ use cache (reading or writing),
+    /// as deleted events are never cached.
     #[inline]
-    pub fn create(conn: &Database, data: &InsertEvent) -> QueryResult<Self> {
+    pub fn find_id_including_deleted(conn: &Database, event_id: i32) -> QueryResult<Self> {
         use crate::schema::event::dsl::*;
 
-        let result: Self = diesel::insert_into(event).values(data).get_result(conn)?;
-        CACHE.lock().insert(result.id, result.clone());
+        event.find(event_id).first(conn)
+    }
 
-        let _ = Message {
-            room:      Room::Thread(result.in_thread_id),
-            action:    Action::Create,
-            data_type: DataType::Event,
-            data:      &result,
+    /// Find a given `Event` by its ID, locking its row until the end of the transaction.
+    /// Returns `NotFound` if it has been deleted.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// as the value must reflect any concurrent changes.
+    #[inline]
+    pub fn find_id_for_update(conn: &Database, event_id: i32) -> QueryResult<Self> {
+        use crate::schema::event::dsl::*;
+
+        event
+            .find(event_id)
+            .filter(deleted_at_utc.is_null())
+            .for_update()
+            .first(conn)
+    }
+
+    /// Find all `Event`s that have not been deleted
+    /// and whose lock expired at or before the provided time.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_expired_locks(conn: &Database, now: i64) -> QueryResult<Vec<Self>> {
+        use crate::schema::event::dsl::*;
+
+        event
+            .filter(deleted_at_utc.is_null())
+            .filter(lock_expires_at_utc.le(now))
+            .load(conn)
+    }
+
+    /// Has the lock on the `Event` been held beyond `LOCK_DURATION_SECONDS`?
+    /// An event that is not locked is considered to have an expired lock.
+    ///
+    /// An expired lock may be taken by any user able to modify the thread.
+    #[inline]
+    pub fn lock_has_expired(&self, now: i64) -> bool {
+        self.lock_expires_at_utc
+            .map_or(true, |expires_at| expires_at <= now)
+    }
+
+    /// Is the `Event` currently locked by a user other than the one provided?
+    ///
+    /// Expired locks are not considered to be held by anyone.
+    #[inline]
+    pub fn is_locked_by_other(&self, user_id: i32) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        self.lock_held_by_user_id
+            .map_or(false, |holder| holder != user_id)
+            && !self.lock_has_expired(now)
+    }
+
+    /// May `actor_id` change the `Event`, given the version the change was based on?
+    ///
+    /// Changes not attributed to any user are never blocked by a lock.
+    #[inline]
+    fn may_change(
+        &self,
+        expected_version: Option<i32>,
+        actor_id: Option<i32>,
+    ) -> Result<(), Rejection> {
+        if actor_id.map_or(false, |actor_id| self.is_locked_by_other(actor_id)) {
+            Err(Rejection::Locked)
+        } else if expected_version.map_or(false, |expected| expected != self.version) {
+            Err(Rejection::VersionMismatch)
+        } else {
+            Ok(())
         }
-        .send();
+    }
 
-        // Add the event ID to the relevant Thread.
-        let mut thread = Thread::find_id(conn, data.in_thread_id)?;
-        thread.events_id.push(result.id);
-        Thread::update(
-            conn,
-            data.in_thread_id,
-            &UpdateThread {
-                events_id: thread.events_id.into(),
-                ..Default::default()
-            },
-        )?;
+    /// Create an `Event` given the data.
+    ///
+    /// The event is inserted and added to its thread in a single transaction.
+    /// Once committed, the inserted row is added to the global cache and returned.
+    /// The creation is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn create(conn: &Database, data: &InsertEvent, actor_id: Option<i32>) -> QueryResult<Self> {
+        use crate::schema::event::dsl::*;
 
-        Ok(result)
+        transaction(conn, |deferred| {
+            // Lock the thread, so that concurrent changes to its ordering aren't lost.
+            let mut thread = Thread::find_id_for_update(conn, data.in_thread_id)?;
+
+            let result: Self = diesel::insert_into(event).values(data).get_result(conn)?;
+            AuditLog::record(conn, actor_id, AuditAction::Create, None, Some(&result))?;
+            EventRevision::record(conn, &result, actor_id)?;
+
+            let cached = result.clone();
+            deferred.push(move || {
+                CACHE.lock().insert(cached.id, cached);
+            });
+            for &target in &[Room::Thread(result.in_thread_id), Room::Event(result.id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Create,
+                    data_type: DataType::Event,
+                    data:      &result,
+                });
+            }
+
+            // Add the event ID to the relevant Thread.
+            thread.events_id.push(result.id);
+            Thread::update_deferred(
+                conn,
+                thread.id,
+                &UpdateThread {
+                    events_id: thread.events_id.into(),
+                    ..Default::default()
+                },
+                actor_id,
+                deferred,
+            )?;
+
+            Ok(result)
+        })
     }
 
     /// Update an `Event` given an ID and the data to update.
     ///
     /// The entry is updated in the database, added to cache, and returned.
+    /// The change is recorded in the audit log as performed by `actor_id`,
+    /// and a new revision is stored if the columns or posted state changed.
+    ///
+    /// No changes are made if another user holds the event's lock,
+    /// or if `expected_version` is provided and the event is no longer at that version.
+    #[inline]
+    pub fn update(
+        conn: &Database,
+        event_id: i32,
+        expected_version: Option<i32>,
+        data: &UpdateEvent,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Result<Self, Rejection>> {
+        transaction(conn, |deferred| {
+            let old = Self::find_id_for_update(conn, event_id)?;
+            if let Err(rejection) = old.may_change(expected_version, actor_id) {
+                return Ok(Err(rejection));
+            }
+
+            Self::update_deferred(conn, &old, data, actor_id, deferred).map(Ok)
+        })
+    }
+
+    /// Update an `Event` whose row has been locked, as part of a larger transaction,
+    /// incrementing its version.
     #[inline]
-    pub fn update(conn: &Database, event_id: i32, data: &UpdateEvent) -> QueryResult<Self> {
+    fn update_deferred(
+        conn: &Database,
+        old: &Self,
+        data: &UpdateEvent,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<Self> {
         use crate::schema::event::dsl::*;
 
+        let event_id = old.id;
         let result: Self = diesel::update(event)
             .filter(id.eq(event_id))
-            .set(data)
+            .set((data, version.eq(version + 1)))
             .get_result(conn)?;
-        CACHE.lock().insert(result.id, result.clone());
+        AuditLog::record(
+            conn,
+            actor_id,
+            AuditAction::Update,
+            Some(old),
+            Some(&result),
+        )?;
+        if old.posted != result.posted || old.cols != result.cols {
+            EventRevision::record(conn, &result, actor_id)?;
+        }
 
-        let _ = Message {
-            room:      Room::Thread(result.in_thread_id),
-            action:    Action::Update,
-            data_type: DataType::Event,
-            data:      &Update::new(event_id, data),
+        let cached = result.clone();
+        deferred.push(move || {
+            CACHE.lock().insert(cached.id, cached);
+        });
+        for &target in &[Room::Thread(result.in_thread_id), Room::Event(result.id)] {
+            deferred.send(Message {
+                room:      target,
+                action:    Action::Update,
+                data_type: DataType::Event,
+                data:      &Update::new(event_id, data),
+            });
         }
-        .send();
 
         Ok(result)
     }
 
+    /// Restore an `Event` to the columns and posted state of a previous revision.
+    ///
+    /// The restore is performed as a normal update,
+    /// so it is sent over WebSockets and stored as a new revision.
+    /// Returns `NotFound` if the revision belongs to a different event.
+    ///
+    /// As with any other update, no changes are made if another user holds the event's lock,
+    /// or if `expected_version` is provided and the event is no longer at that version.
+    #[inline]
+    pub fn restore(
+        conn: &Database,
+        event_id: i32,
+        revision_id: i32,
+        expected_version: Option<i32>,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Result<Self, Rejection>> {
+        let revision = EventRevision::find_id(conn, revision_id)?;
+        if revision.event_id != event_id {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        Self::update(
+            conn,
+            event_id,
+            expected_version,
+            &UpdateEvent {
+                posted: Some(revision.posted),
+                cols:   Some(revision.cols),
+            },
+            actor_id,
+        )
+    }
+
+    /// Set a lock on an `Event`.
+    /// Whether the actor is able to modify the thread is _not_ verified here.
+    ///
+    /// The entry is updated in the database and returned.
+    /// Once committed, it is added to cache and clients are notified.
+    /// The change is recorded in the audit log as performed by `actor`.
+    ///
+    /// No changes are made if `actor` may not set the lock as requested,
+    /// which is checked against the current holder of the lock.
+    ///
+    /// If the previous holder of the lock lost it to someone else or to its expiry,
+    /// they are notified in their private room.
+    #[inline]
+    pub fn set_lock(
+        conn: &Database,
+        event_id: i32,
+        data: &LockEvent,
+        actor: &User,
+    ) -> QueryResult<Result<Self, Rejection>> {
+        use crate::schema::event::dsl::*;
+
+        transaction(conn, |deferred| {
+            let old = Self::find_id_for_update(conn, event_id)?;
+            if !may_set_lock(
+                actor,
+                old.lock_held_by_user_id,
+                old.lock_has_expired(data.lock_assigned_at_utc),
+                data.lock_held_by_user_id,
+            ) {
+                return Ok(Err(Rejection::Forbidden));
+            }
+
+            let result: Self = diesel::update(event)
+                .filter(id.eq(event_id))
+                .set((data, version.eq(version + 1)))
+                .get_result(conn)?;
+            Self::record_lock(conn, &old, &result, data, Some(actor.id), deferred)?;
+
+            Ok(Ok(result))
+        })
+    }
+
+    /// Release the lock on an `Event` if it has expired by `now`,
+    /// notifying clients as if the holder had released the lock themselves.
+    /// The change is not attributed to any user.
+    ///
+    /// Returns `None` without making any changes if the event has been deleted,
+    /// or if the lock has since been renewed or taken by someone else.
+    #[inline]
+    pub fn release_expired_lock(
+        conn: &Database,
+        event_id: i32,
+        now: i64,
+    ) -> QueryResult<Option<Self>> {
+        use crate::schema::event::dsl::*;
+
+        let data = LockEvent::new(None, now);
+
+        transaction(conn, |deferred| {
+            let old = match Self::find_id_for_update(conn, event_id).optional()? {
+                Some(old) => old,
+                None => return Ok(None),
+            };
+
+            let result: Option<Self> = diesel::update(event)
+                .filter(id.eq(event_id))
+                .filter(lock_expires_at_utc.le(now))
+                .set((&data, version.eq(version + 1)))
+                .get_result(conn)
+                .optional()?;
+            if let Some(result) = &result {
+                Self::record_lock(conn, &old, result, &data, None, deferred)?;
+            }
+
+            Ok(result)
+        })
+    }
+
+    /// Record a change to the lock on an `Event` as part of a larger transaction.
+    ///
+    /// Writing the entry to cache and notifying clients
+    /// are deferred until the transaction has been committed.
+    /// If the previous holder of the lock lost it to someone else or to its expiry,
+    /// they are notified in their private room.
+    #[inline]
+    fn record_lock(
+        conn: &Database,
+        old: &Self,
+        result: &Self,
+        data: &LockEvent,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<()> {
+        AuditLog::record(conn, actor_id, AuditAction::Lock, Some(old), Some(result))?;
+
+        let cached = result.clone();
+        deferred.push(move || {
+            CACHE.lock().insert(cached.id, cached);
+        });
+        for &target in &[Room::Thread(result.in_thread_id), Room::Event(result.id)] {
+            deferred.send(Message {
+                room:      target,
+                action:    Action::Update,
+                data_type: DataType::Event,
+                data:      &Update::new(result.id, data),
+            });
+        }
+
+        if let Some(holder) = old.lock_held_by_user_id {
+            if result.lock_held_by_user_id != Some(holder) && actor_id != Some(holder) {
+                deferred.send(Message {
+                    room:      Room::Private(holder),
+                    action:    Action::LockLost,
+                    data_type: DataType::Event,
+                    data:      &Update::new(result.id, data),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// Delete an `Event` given its ID.
     ///
+    /// The event is only marked as deleted,
+    /// remembering its position in the thread so that it can later be undeleted.
     /// Removes the entry from cache and returns the number of rows deleted (should be `1`).
+    /// The deletion is recorded in the audit log as performed by `actor_id`.
+    ///
+    /// No changes are made if another user holds the event's lock,
+    /// or if `expected_version` is provided and the event is no longer at that version.
     #[inline]
-    pub fn delete(conn: &Database, event_id: i32) -> QueryResult<usize> {
+    pub fn delete(
+        conn: &Database,
+        event_id: i32,
+        expected_version: Option<i32>,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Result<usize, Rejection>> {
         use crate::schema::event::dsl::*;
 
-        let mut thread = Thread::find_id(conn, Event::find_id(conn, event_id)?.in_thread_id)?;
-        thread.events_id.retain(|&cur_id| cur_id != event_id);
-        Thread::update(
-            conn,
-            thread.id,
-            &UpdateThread {
-                events_id: thread.events_id.into(),
-                ..Default::default()
-            },
-        )?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
 
-        let _ = Message {
-            room:      Room::Thread(thread.id),
-            action:    Action::Delete,
-            data_type: DataType::Event,
-            data:      &json!({ "id": event_id }),
+        transaction(conn, |deferred| {
+            // Lock the thread before the event, matching the order used when deleting a thread.
+            let thread_id = Self::find_id(conn, event_id)?.in_thread_id;
+            let mut thread = Thread::find_id_for_update(conn, thread_id)?;
+            let old = Self::find_id_for_update(conn, event_id)?;
+            if let Err(rejection) = old.may_change(expected_version, actor_id) {
+                return Ok(Err(rejection));
+            }
+
+            let position = thread
+                .events_id
+                .iter()
+                .position(|&cur_id| cur_id == event_id);
+            thread.events_id.retain(|&cur_id| cur_id != event_id);
+            Thread::update_deferred(
+                conn,
+                thread.id,
+                &UpdateThread {
+                    events_id: thread.events_id.into(),
+                    ..Default::default()
+                },
+                actor_id,
+                deferred,
+            )?;
+            AuditLog::record(conn, actor_id, AuditAction::Delete, Some(&old), None)?;
+
+            deferred.push(move || {
+                CACHE.lock().remove(&event_id);
+            });
+            for &target in &[Room::Thread(thread.id), Room::Event(event_id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Delete,
+                    data_type: DataType::Event,
+                    data:      &json!({ "id": event_id }),
+                });
+            }
+
+            diesel::update(event)
+                .filter(id.eq(event_id))
+                .set((
+                    deleted_at_utc.eq(now),
+                    version.eq(version + 1),
+                    deleted_position.eq(position.map(|position| position as i32)),
+                ))
+                .execute(conn)
+                .map(Ok)
+        })
+    }
+
+    /// Undelete an `Event` given its ID,
+    /// re-inserting it into its thread at the position it was deleted from.
+    ///
+    /// The entry is added to the global cache and returned.
+    /// The undeletion is recorded in the audit log as performed by `actor_id`.
+    /// Returns `NotFound` if the event's thread has been deleted.
+    #[inline]
+    pub fn undelete(conn: &Database, event_id: i32, actor_id: Option<i32>) -> QueryResult<Self> {
+        use crate::schema::event::dsl::*;
+
+        let old = Self::find_id_including_deleted(conn, event_id)?;
+        if old.deleted_at_utc.is_none() {
+            return Ok(old);
+        }
+
+        transaction(conn, |deferred| {
+            let mut thread = Thread::find_id_for_update(conn, old.in_thread_id)?;
+
+            let result: Self = diesel::update(event)
+                .filter(id.eq(event_id))
+                .set((
+                    deleted_at_utc.eq(None::<i64>),
+                    deleted_position.eq(None::<i32>),
+                    version.eq(version + 1),
+                ))
+                .get_result(conn)?;
+            AuditLog::record(
+                conn,
+                actor_id,
+                AuditAction::Undelete,
+                Some(&old),
+                Some(&result),
+            )?;
+
+            let cached = result.clone();
+            deferred.push(move || {
+                CACHE.lock().insert(cached.id, cached);
+            });
+            for &target in &[Room::Thread(result.in_thread_id), Room::Event(result.id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Create,
+                    data_type: DataType::Event,
+                    data:      &result,
+                });
+            }
+
+            let position = old
+                .deleted_position
+                .map_or(thread.events_id.len(), |position| {
+                    (position as usize).min(thread.events_id.len())
+                });
+            thread.events_id.insert(position, result.id);
+            Thread::update_deferred(
+                conn,
+                thread.id,
+                &UpdateThread {
+                    events_id: thread.events_id.into(),
+                    ..Default::default()
+                },
+                actor_id,
+                deferred,
+            )?;
+
+            Ok(result)
+        })
+    }
+
+    /// Delete all `Event`s belonging to a `Thread` as part of deleting the thread,
+    /// marking them as deleted at the same time as the thread.
+    ///
+    /// The thread's ordering is left untouched,
+    /// so that the events can be undeleted along with the thread.
+    #[inline]
+    pub fn delete_by_thread(
+        conn: &Database,
+        thread_id: i32,
+        deleted_at: i64,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<usize> {
+        use crate::schema::event::dsl::*;
+
+        let values: Vec<Self> = event
+            .filter(in_thread_id.eq(thread_id))
+            .filter(deleted_at_utc.is_null())
+            .for_update()
+            .load(conn)?;
+
+        for value in values.iter() {
+            AuditLog::record(conn, actor_id, AuditAction::Delete, Some(value), None)?;
+
+            let value_id = value.id;
+            deferred.push(move || {
+                CACHE.lock().remove(&value_id);
+            });
+            for &target in &[Room::Thread(thread_id), Room::Event(value_id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Delete,
+                    data_type: DataType::Event,
+                    data:      &json!({ "id": value_id }),
+                });
+            }
         }
-        .send();
 
-        CACHE.lock().remove(&event_id);
-        diesel::delete(event).filter(id.eq(event_id)).execute(conn)
+        diesel::update(event)
+            .filter(in_thread_id.eq(thread_id))
+            .filter(deleted_at_utc.is_null())
+            .set((deleted_at_utc.eq(deleted_at), version.eq(version + 1)))
+            .execute(conn)
+    }
+
+    /// Undelete all `Event`s that were deleted along with their `Thread`.
+    #[inline]
+    pub fn undelete_by_thread(
+        conn: &Database,
+        thread_id: i32,
+        deleted_at: i64,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<usize> {
+        use crate::schema::event::dsl::*;
+
+        let values: Vec<Self> = diesel::update(event)
+            .filter(in_thread_id.eq(thread_id))
+            .filter(deleted_at_utc.eq(deleted_at))
+            .filter(deleted_position.is_null())
+            .set((deleted_at_utc.eq(None::<i64>), version.eq(version + 1)))
+            .get_results(conn)?;
+
+        for value in values.iter() {
+            AuditLog::record(conn, actor_id, AuditAction::Undelete, None, Some(value))?;
+
+            for &target in &[Room::Thread(thread_id), Room::Event(value.id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Create,
+                    data_type: DataType::Event,
+                    data:      value,
+                });
+            }
+        }
+
+        let count = values.len();
+        deferred.push(move || {
+            let mut cache = CACHE.lock();
+            for value in values {
+                cache.insert(value.id, value);
+            }
+        });
+
+        Ok(count)
+    }
+}
+
+impl Audited for Event {
+    #[inline]
+    fn audit_entity(&self) -> AuditEntity {
+        AuditEntity {
+            data_type: DataType::Event,
+            id:        self.id,
+            thread_id: Some(self.in_thread_id),
+        }
     }
 }
 
@@ -0,0 +1,76 @@
+use super::Event;
+use crate::{
+    schema::event_revision::{self, dsl::*},
+    Database,
+};
+use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use serde::Serialize;
+use serde_json::Value as Json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The columns and posted state of an `Event` at a point in time.
+///
+/// A revision is stored when the event is created
+/// and whenever either field is changed.
+/// `created_by_user_id` is `None` if the change was made by the API itself,
+/// or if the user has since been deleted.
+#[derive(Debug, Clone, Serialize, Queryable)]
+pub struct EventRevision {
+    pub id: i32,
+    pub event_id: i32,
+    pub posted: bool,
+    pub cols: Json,
+    pub created_by_user_id: Option<i32>,
+    pub created_at_utc: i64,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "event_revision"]
+struct InsertEventRevision<'a> {
+    event_id: i32,
+    posted: bool,
+    cols: &'a Json,
+    created_by_user_id: Option<i32>,
+    created_at_utc: i64,
+}
+
+impl EventRevision {
+    /// Find all revisions of a given `Event`, newest first.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// as revisions are only read on explicit request.
+    #[inline]
+    pub fn find_by_event(conn: &Database, for_event_id: i32) -> QueryResult<Vec<Self>> {
+        event_revision
+            .filter(event_id.eq(for_event_id))
+            .order(id.desc())
+            .load(conn)
+    }
+
+    /// Find a given `EventRevision` by its ID.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_id(conn: &Database, revision_id: i32) -> QueryResult<Self> {
+        event_revision.find(revision_id).first(conn)
+    }
+
+    /// Store the current columns and posted state of an `Event` as a new revision.
+    #[inline]
+    pub fn record(conn: &Database, value: &Event, actor_id: Option<i32>) -> QueryResult<Self> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        diesel::insert_into(event_revision)
+            .values(&InsertEventRevision {
+                event_id: value.id,
+                posted: value.posted,
+                cols: &value.cols,
+                created_by_user_id: actor_id,
+                created_at_utc: now,
+            })
+            .get_result(conn)
+    }
+}
@@ -1,23 +1,87 @@
 use crate::Database;
 use std::error::Error;
 
+const COMMENT_CACHE_SIZE: usize = 20;
 const EVENT_CACHE_SIZE: usize = 100;
 const SECTION_CACHE_SIZE: usize = 50;
 const THREAD_CACHE_SIZE: usize = 5;
 const USER_CACHE_SIZE: usize = 100;
+const USER_SUBREDDIT_ROLE_CACHE_SIZE: usize = 100;
+
+/// How long are section and event locks able to be held for, guaranteed?
+///
+/// Currently 10 minutes,
+/// although this is an implementation detail and should not be relied upon.
+pub const LOCK_DURATION_SECONDS: i64 = 10 * 60;
+
+/// Why a change to an entity was not made.
+///
+/// Each is checked against the row locked by the transaction making the change,
+/// so that a concurrent change can't slip in between the check and the update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// The entity is no longer at the version the change was based on.
+    VersionMismatch,
+    /// Another user holds an unexpired lock on the entity.
+    Locked,
+    /// The user may not set the entity's lock as requested.
+    Forbidden,
+    /// The change would add or remove IDs from an ordering, rather than only reorder them.
+    OrderingMismatch,
+}
+
+/// May the user set the lock on a section or event,
+/// currently held by `held_by`, to `requested`?
+///
+/// Whether the user is able to modify the thread is _not_ verified here.
+#[inline]
+pub fn may_set_lock(
+    user: &User,
+    held_by: Option<i32>,
+    has_expired: bool,
+    requested: Option<i32>,
+) -> bool {
+    // (1) Let the user assign the (currently null) lock to themselves.
+    // (2) Let the user revoke their own lock.
+    // (3) Let the user renew their own lock.
+    // (4) A user holds a lock, but it has been held beyond the specified minimum duration.
+    //     Allow the requesting user to possess the lock.
+    // (5) A global admin is breaking the lock, either revoking it or taking it for themselves.
+    (held_by.is_none() && requested == Some(user.id))
+        || (held_by == Some(user.id) && requested.is_none())
+        || (held_by == Some(user.id) && requested == Some(user.id))
+        || has_expired
+        || (user.is_global_admin && requested.map_or(true, |user_id| user_id == user.id))
+}
 
 pub trait ToMarkdown {
     fn to_markdown(&self, conn: &Database) -> Result<String, Box<dyn Error>>;
 }
 
+mod audit_log;
 mod claim;
+mod comment;
+mod deferred;
 mod event;
+mod event_revision;
+mod overflow;
+mod reddit_sync;
 mod section;
+mod section_revision;
 mod thread;
 mod user;
+mod user_subreddit_role;
 
+pub use audit_log::*;
 pub use claim::*;
+pub use comment::*;
+pub use deferred::*;
 pub use event::*;
+pub use event_revision::*;
+pub use overflow::*;
+pub use reddit_sync::*;
 pub use section::*;
+pub use section_revision::*;
 pub use thread::*;
 pub use user::*;
+pub use user_subreddit_role::*;
@@ -0,0 +1,90 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::env;
+
+lazy_static! {
+    /// The maximum number of characters a thread may be rendered to.
+    /// Defaults to Reddit's own limit on the length of a self post.
+    pub static ref SELFTEXT_LIMIT: usize = env::var("REDDIT_SELFTEXT_LIMIT")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(reddit::MAX_SELFTEXT_LENGTH);
+
+    /// What to do when a thread exceeds `SELFTEXT_LIMIT`.
+    /// Defaults to truncating the oldest events.
+    pub static ref OVERFLOW_STRATEGY: OverflowStrategy = env::var("REDDIT_OVERFLOW_STRATEGY")
+        .ok()
+        .and_then(|strategy| strategy.parse().ok())
+        .unwrap_or(OverflowStrategy::TruncateEvents);
+}
+
+/// Appended to a thread when some of its sections have been moved into a comment.
+pub const CONTINUATION_NOTICE: &str = "*Continued in the comments.*";
+
+/// How a thread is shortened when its markdown is longer than permitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowStrategy {
+    /// Leave out the oldest posted events until the thread fits.
+    TruncateEvents,
+    /// Replace sections with a short notice, largest first, until the thread fits.
+    CollapseSections,
+    /// Move sections that don't fit into a stickied comment on the thread.
+    Continuation,
+}
+
+impl std::str::FromStr for OverflowStrategy {
+    type Err = ();
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "truncate_events" => Ok(OverflowStrategy::TruncateEvents),
+            "collapse_sections" => Ok(OverflowStrategy::CollapseSections),
+            "continuation" => Ok(OverflowStrategy::Continuation),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The action taken to make a thread fit within the length limit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum Overflow {
+    /// The oldest posted events were left out.
+    TruncateEvents { omitted_events: usize },
+    /// The listed sections were replaced with a short notice.
+    CollapseSections { collapsed_sections_id: Vec<i32> },
+    /// The listed sections were moved into the thread's continuation comment.
+    Continuation { continued_sections_id: Vec<i32> },
+    /// The configured strategy was not sufficient,
+    /// so the markdown was cut off at the limit.
+    Truncate,
+}
+
+/// A thread rendered to markdown suitable for Reddit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendered {
+    pub markdown: String,
+    /// The action taken to shorten the markdown, if any.
+    pub overflow: Option<Overflow>,
+}
+
+/// Is the markdown no longer than `limit` characters?
+#[inline]
+pub fn fits(md: &str, limit: usize) -> bool {
+    md.chars().count() <= limit
+}
+
+/// Cut the markdown off at `limit` characters.
+///
+/// Where possible, the cut is made at the end of a line,
+/// avoiding a partial row in any table.
+#[inline]
+pub fn truncate(md: &str, limit: usize) -> String {
+    let truncated: String = md.chars().take(limit).collect();
+
+    match truncated.rfind('\n') {
+        Some(index) => truncated[..=index].to_owned(),
+        None => truncated,
+    }
+}
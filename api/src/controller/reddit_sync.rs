@@ -0,0 +1,54 @@
+use crate::{
+    schema::reddit_sync::{self, dsl::*},
+    Database,
+};
+use enceladus_macros::generate_structs;
+use rocket_contrib::databases::diesel::{
+    ExpressionMethods,
+    OptionalExtension,
+    QueryDsl,
+    QueryResult,
+    RunQueryDsl,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+generate_structs! {
+    RedditSync("reddit_sync") {
+        readonly thread_id: i32,
+        private markdown: String,
+        synced_at_utc: i64,
+    }
+}
+
+impl RedditSync {
+    /// Find the last successful push of a given `Thread` to Reddit,
+    /// if it has ever been pushed.
+    ///
+    /// Does _not_ use cache,
+    /// as the value is only read when pushing or on explicit request.
+    #[inline]
+    pub fn find_id(conn: &Database, id: i32) -> QueryResult<Option<Self>> {
+        reddit_sync.find(id).first(conn).optional()
+    }
+
+    /// Record the markdown that was just pushed to Reddit for a given `Thread`,
+    /// replacing any previous record.
+    #[inline]
+    pub fn record(conn: &Database, id: i32, md: &str) -> QueryResult<Self> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        diesel::insert_into(reddit_sync)
+            .values(&InsertRedditSync {
+                thread_id:     id,
+                markdown:      md.to_owned(),
+                synced_at_utc: now,
+            })
+            .on_conflict(thread_id)
+            .do_update()
+            .set((markdown.eq(md), synced_at_utc.eq(now)))
+            .get_result(conn)
+    }
+}
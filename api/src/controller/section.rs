@@ -1,4 +1,22 @@
-use super::{Event, Thread, ToMarkdown, UpdateThread, SECTION_CACHE_SIZE};
+use super::{
+    may_set_lock,
+    transaction,
+    AuditAction,
+    AuditEntity,
+    AuditLog,
+    Audited,
+    Comment,
+    Deferred,
+    Event,
+    Rejection,
+    SectionRevision,
+    Thread,
+    ToMarkdown,
+    UpdateThread,
+    User,
+    LOCK_DURATION_SECONDS,
+    SECTION_CACHE_SIZE,
+};
 use crate::{
     schema::section::{self, dsl::*},
     websocket::*,
@@ -9,10 +27,21 @@ use itertools::Itertools;
 use lazy_static::lazy_static;
 use lru_cache::LruCache;
 use parking_lot::Mutex;
-use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use rocket_contrib::databases::diesel::{
+    ExpressionMethods,
+    OptionalExtension,
+    QueryDsl,
+    QueryResult,
+    RunQueryDsl,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::{error::Error, fmt::Write, iter::repeat};
+use std::{
+    error::Error,
+    fmt::Write,
+    iter::repeat,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 lazy_static! {
     /// A global cache, containing a mapping of IDs to their respective `Event`.
@@ -30,6 +59,7 @@ lazy_static! {
 
 // Fields relating to the lock are not necessarily `auto`,
 // but are declared as such as they are handled by the `LockSection` struct.
+// Likewise, fields relating to deletion are handled by `delete` and `undelete`.
 generate_structs! {
     Section("section") {
         auto id: i32,
@@ -39,6 +69,10 @@ generate_structs! {
         auto lock_held_by_user_id: Option<i32>,
         readonly in_thread_id: i32,
         auto lock_assigned_at_utc: i64,
+        auto deleted_at_utc: Option<i64>,
+        auto deleted_position: Option<i32>,
+        auto version: i32,
+        auto lock_expires_at_utc: Option<i64>,
     }
 }
 
@@ -50,24 +84,50 @@ pub struct ExternalLockSection {
 }
 
 /// Only these fields may be internally present when setting a section's lock.
+///
+/// A `None` lock holder is written as `NULL`, releasing the lock.
 #[derive(Serialize, AsChangeset, Debug)]
 #[table_name = "section"]
+#[changeset_options(treat_none_as_null = "true")]
 pub struct LockSection {
     pub lock_held_by_user_id: Option<i32>,
     pub lock_assigned_at_utc: i64,
+    pub lock_expires_at_utc:  Option<i64>,
+}
+
+impl LockSection {
+    /// Assign the lock to the provided user (or nobody) at the given time,
+    /// expiring `LOCK_DURATION_SECONDS` later if held.
+    #[inline]
+    pub fn new(user_id: Option<i32>, now: i64) -> Self {
+        LockSection {
+            lock_held_by_user_id: user_id,
+            lock_assigned_at_utc: now,
+            lock_expires_at_utc:  user_id.map(|_| now + LOCK_DURATION_SECONDS),
+        }
+    }
 }
 
 impl Section {
-    /// Find all `Section`s in the database.
+    /// Find all `Section`s in the database that have not been deleted.
     ///
     /// Does _not_ use cache (reading or writing),
     /// so as to avoid storing values rarely accessed.
     #[inline]
     pub fn find_all(conn: &Database) -> QueryResult<Vec<Self>> {
+        section.filter(deleted_at_utc.is_null()).load(conn)
+    }
+
+    /// Find all `Section`s in the database, including those that have been deleted.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_all_including_deleted(conn: &Database) -> QueryResult<Vec<Self>> {
         section.load(conn)
     }
 
-    /// Find a given `Section` by its ID.
+    /// Find a given `Section` by its ID,
+    /// returning `NotFound` if it has been deleted.
     ///
     /// Internally uses a cache to limit database accesses.
     #[inline]
@@ -76,125 +136,594 @@ impl Section {
         if cache.contains_key(&section_id) {
             Ok(cache.get_mut(&section_id).unwrap().clone())
         } else {
-            let result: Self = section.find(section_id).first(conn)?;
+            let result: Self = section
+                .find(section_id)
+                .filter(deleted_at_utc.is_null())
+                .first(conn)?;
             cache.insert(section_id, result.clone());
             Ok(result)
         }
     }
 
-    /// Create a `Section` given the data.
+    /// Find a given `Section` by its ID, even if it has been deleted.
     ///
-    /// The inserted row is added to the global cache and returned.
+    /// Does _not_ use cache (reading or writing),
+    /// as deleted sections are never cached.
     #[inline]
-    pub fn create(conn: &Database, data: &InsertSection) -> QueryResult<Self> {
-        let result: Self = diesel::insert_into(section).values(data).get_result(conn)?;
-        CACHE.lock().insert(result.id, result.clone());
+    pub fn find_id_including_deleted(conn: &Database, section_id: i32) -> QueryResult<Self> {
+        section.find(section_id).first(conn)
+    }
 
-        let _ = Message {
-            room:      Room::Thread(result.in_thread_id),
-            action:    Action::Create,
-            data_type: DataType::Section,
-            data:      &result,
+    /// Find a given `Section` by its ID, locking its row until the end of the transaction.
+    /// Returns `NotFound` if it has been deleted.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// as the value must reflect any concurrent changes.
+    #[inline]
+    pub fn find_id_for_update(conn: &Database, section_id: i32) -> QueryResult<Self> {
+        section
+            .find(section_id)
+            .filter(deleted_at_utc.is_null())
+            .for_update()
+            .first(conn)
+    }
+
+    /// Find all `Section`s that have not been deleted
+    /// and whose lock expired at or before the provided time.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_expired_locks(conn: &Database, now: i64) -> QueryResult<Vec<Self>> {
+        section
+            .filter(deleted_at_utc.is_null())
+            .filter(lock_expires_at_utc.le(now))
+            .load(conn)
+    }
+
+    /// Has the lock on the `Section` been held beyond `LOCK_DURATION_SECONDS`?
+    /// A section that is not locked is considered to have an expired lock.
+    ///
+    /// An expired lock may be taken by any user able to modify the thread.
+    #[inline]
+    pub fn lock_has_expired(&self, now: i64) -> bool {
+        self.lock_expires_at_utc
+            .map_or(true, |expires_at| expires_at <= now)
+    }
+
+    /// Is the `Section` currently locked by a user other than the one provided?
+    ///
+    /// Expired locks are not considered to be held by anyone.
+    #[inline]
+    pub fn is_locked_by_other(&self, user_id: i32) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        self.lock_held_by_user_id
+            .map_or(false, |holder| holder != user_id)
+            && !self.lock_has_expired(now)
+    }
+
+    /// May `actor_id` change the `Section`, given the version the change was based on?
+    ///
+    /// Changes not attributed to any user are never blocked by a lock.
+    #[inline]
+    fn may_change(
+        &self,
+        expected_version: Option<i32>,
+        actor_id: Option<i32>,
+    ) -> Result<(), Rejection> {
+        if actor_id.map_or(false, |actor_id| self.is_locked_by_other(actor_id)) {
+            Err(Rejection::Locked)
+        } else if expected_version.map_or(false, |expected| expected != self.version) {
+            Err(Rejection::VersionMismatch)
+        } else {
+            Ok(())
         }
-        .send();
+    }
 
-        // Add the section ID to the relevant Thread.
-        let mut thread = Thread::find_id(conn, data.in_thread_id)?;
-        thread.sections_id.push(result.id);
-        Thread::update(
-            conn,
-            data.in_thread_id,
-            &UpdateThread {
-                sections_id: thread.sections_id.into(),
-                ..Default::default()
-            },
-        )?;
+    /// Create a `Section` given the data.
+    ///
+    /// The section is inserted and added to its thread in a single transaction.
+    /// Once committed, the inserted row is added to the global cache and returned.
+    /// The creation is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn create(
+        conn: &Database,
+        data: &InsertSection,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Self> {
+        transaction(conn, |deferred| {
+            // Lock the thread, so that concurrent changes to its ordering aren't lost.
+            let mut thread = Thread::find_id_for_update(conn, data.in_thread_id)?;
 
-        Ok(result)
+            let result: Self = diesel::insert_into(section).values(data).get_result(conn)?;
+            AuditLog::record(conn, actor_id, AuditAction::Create, None, Some(&result))?;
+            SectionRevision::record(conn, &result, actor_id)?;
+
+            let cached = result.clone();
+            deferred.push(move || {
+                CACHE.lock().insert(cached.id, cached);
+            });
+            for &target in &[Room::Thread(result.in_thread_id), Room::Section(result.id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Create,
+                    data_type: DataType::Section,
+                    data:      &result,
+                });
+            }
+
+            // Add the section ID to the relevant Thread.
+            thread.sections_id.push(result.id);
+            Thread::update_deferred(
+                conn,
+                thread.id,
+                &UpdateThread {
+                    sections_id: thread.sections_id.into(),
+                    ..Default::default()
+                },
+                actor_id,
+                deferred,
+            )?;
+
+            Ok(result)
+        })
     }
 
     /// Update a `Section` given an ID and the data to update.
     ///
     /// The entry is updated in the database, added to cache, and returned.
+    /// The change is recorded in the audit log as performed by `actor_id`,
+    /// and a new revision is stored if the name or content changed.
+    ///
+    /// No changes are made if another user holds the section's lock,
+    /// or if `expected_version` is provided and the section is no longer at that version.
+    #[inline]
+    pub fn update(
+        conn: &Database,
+        section_id: i32,
+        expected_version: Option<i32>,
+        data: &UpdateSection,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Result<Self, Rejection>> {
+        transaction(conn, |deferred| {
+            let old = Self::find_id_for_update(conn, section_id)?;
+            if let Err(rejection) = old.may_change(expected_version, actor_id) {
+                return Ok(Err(rejection));
+            }
+
+            Self::update_deferred(conn, &old, data, actor_id, deferred).map(Ok)
+        })
+    }
+
+    /// Update a `Section` whose row has been locked, as part of a larger transaction,
+    /// incrementing its version.
     #[inline]
-    pub fn update(conn: &Database, section_id: i32, data: &UpdateSection) -> QueryResult<Self> {
+    fn update_deferred(
+        conn: &Database,
+        old: &Self,
+        data: &UpdateSection,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<Self> {
+        let section_id = old.id;
         let result: Self = diesel::update(section)
             .filter(id.eq(section_id))
-            .set(data)
+            .set((data, version.eq(version + 1)))
             .get_result(conn)?;
-        CACHE.lock().insert(result.id, result.clone());
+        AuditLog::record(
+            conn,
+            actor_id,
+            AuditAction::Update,
+            Some(old),
+            Some(&result),
+        )?;
+        if old.name != result.name || old.content != result.content {
+            SectionRevision::record(conn, &result, actor_id)?;
+        }
 
-        let _ = Message {
-            room:      Room::Thread(result.in_thread_id),
-            action:    Action::Update,
-            data_type: DataType::Section,
-            data:      &Update::new(section_id, data),
+        let cached = result.clone();
+        deferred.push(move || {
+            CACHE.lock().insert(cached.id, cached);
+        });
+        for &target in &[Room::Thread(result.in_thread_id), Room::Section(result.id)] {
+            deferred.send(Message {
+                room:      target,
+                action:    Action::Update,
+                data_type: DataType::Section,
+                data:      &Update::new(section_id, data),
+            });
         }
-        .send();
 
         Ok(result)
     }
 
+    /// Restore a `Section` to the name and content of a previous revision.
+    ///
+    /// The restore is performed as a normal update,
+    /// so it is sent over WebSockets and stored as a new revision.
+    /// Returns `NotFound` if the revision belongs to a different section.
+    ///
+    /// As with any other update, no changes are made if another user holds the section's lock,
+    /// or if `expected_version` is provided and the section is no longer at that version.
+    #[inline]
+    pub fn restore(
+        conn: &Database,
+        section_id: i32,
+        revision_id: i32,
+        expected_version: Option<i32>,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Result<Self, Rejection>> {
+        let revision = SectionRevision::find_id(conn, revision_id)?;
+        if revision.section_id != section_id {
+            return Err(diesel::result::Error::NotFound);
+        }
+
+        Self::update(
+            conn,
+            section_id,
+            expected_version,
+            &UpdateSection {
+                name:    Some(revision.name),
+                content: Some(revision.content),
+            },
+            actor_id,
+        )
+    }
+
     /// Set a lock on a `Section`.
-    /// Integrity and authority to perform this action is _not_ verified here.
+    /// Whether the actor is able to modify the thread is _not_ verified here.
     ///
-    /// The entry is updated in the database, added to cache, and returned.
+    /// The entry is updated in the database and returned.
+    /// Once committed, it is added to cache and clients are notified.
+    /// The change is recorded in the audit log as performed by `actor`.
+    ///
+    /// No changes are made if `actor` may not set the lock as requested,
+    /// which is checked against the current holder of the lock.
+    ///
+    /// If the previous holder of the lock lost it to someone else or to its expiry,
+    /// they are notified in their private room.
     #[inline]
-    pub fn set_lock(conn: &Database, section_id: i32, data: &LockSection) -> QueryResult<Self> {
-        let result: Self = diesel::update(section)
-            .filter(id.eq(section_id))
-            .set(data)
-            .get_result(conn)?;
-        CACHE.lock().insert(result.id, result.clone());
+    pub fn set_lock(
+        conn: &Database,
+        section_id: i32,
+        data: &LockSection,
+        actor: &User,
+    ) -> QueryResult<Result<Self, Rejection>> {
+        transaction(conn, |deferred| {
+            let old = Self::find_id_for_update(conn, section_id)?;
+            if !may_set_lock(
+                actor,
+                old.lock_held_by_user_id,
+                old.lock_has_expired(data.lock_assigned_at_utc),
+                data.lock_held_by_user_id,
+            ) {
+                return Ok(Err(Rejection::Forbidden));
+            }
 
-        let _ = Message {
-            room:      Room::Thread(result.in_thread_id),
-            action:    Action::Update,
-            data_type: DataType::Section,
-            data:      &Update::new(section_id, data),
+            let result: Self = diesel::update(section)
+                .filter(id.eq(section_id))
+                .set((data, version.eq(version + 1)))
+                .get_result(conn)?;
+            Self::record_lock(conn, &old, &result, data, Some(actor.id), deferred)?;
+
+            Ok(Ok(result))
+        })
+    }
+
+    /// Release the lock on a `Section` if it has expired by `now`,
+    /// notifying clients as if the holder had released the lock themselves.
+    /// The change is not attributed to any user.
+    ///
+    /// Returns `None` without making any changes if the section has been deleted,
+    /// or if the lock has since been renewed or taken by someone else.
+    #[inline]
+    pub fn release_expired_lock(
+        conn: &Database,
+        section_id: i32,
+        now: i64,
+    ) -> QueryResult<Option<Self>> {
+        let data = LockSection::new(None, now);
+
+        transaction(conn, |deferred| {
+            let old = match Self::find_id_for_update(conn, section_id).optional()? {
+                Some(old) => old,
+                None => return Ok(None),
+            };
+
+            let result: Option<Self> = diesel::update(section)
+                .filter(id.eq(section_id))
+                .filter(lock_expires_at_utc.le(now))
+                .set((&data, version.eq(version + 1)))
+                .get_result(conn)
+                .optional()?;
+            if let Some(result) = &result {
+                Self::record_lock(conn, &old, result, &data, None, deferred)?;
+            }
+
+            Ok(result)
+        })
+    }
+
+    /// Record a change to the lock on a `Section` as part of a larger transaction.
+    ///
+    /// Writing the entry to cache and notifying clients
+    /// are deferred until the transaction has been committed.
+    /// If the previous holder of the lock lost it to someone else or to its expiry,
+    /// they are notified in their private room.
+    #[inline]
+    fn record_lock(
+        conn: &Database,
+        old: &Self,
+        result: &Self,
+        data: &LockSection,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<()> {
+        AuditLog::record(conn, actor_id, AuditAction::Lock, Some(old), Some(result))?;
+
+        let cached = result.clone();
+        deferred.push(move || {
+            CACHE.lock().insert(cached.id, cached);
+        });
+        for &target in &[Room::Thread(result.in_thread_id), Room::Section(result.id)] {
+            deferred.send(Message {
+                room:      target,
+                action:    Action::Update,
+                data_type: DataType::Section,
+                data:      &Update::new(result.id, data),
+            });
         }
-        .send();
 
-        Ok(result)
+        if let Some(holder) = old.lock_held_by_user_id {
+            if result.lock_held_by_user_id != Some(holder) && actor_id != Some(holder) {
+                deferred.send(Message {
+                    room:      Room::Private(holder),
+                    action:    Action::LockLost,
+                    data_type: DataType::Section,
+                    data:      &Update::new(result.id, data),
+                });
+            }
+        }
+
+        Ok(())
     }
 
     /// Delete a `Section` given its ID.
     ///
+    /// The section is only marked as deleted,
+    /// remembering its position in the thread so that it can later be undeleted.
     /// Removes the entry from cache and returns the number of rows deleted (should be `1`).
+    /// The deletion is recorded in the audit log as performed by `actor_id`.
+    ///
+    /// No changes are made if another user holds the section's lock,
+    /// or if `expected_version` is provided and the section is no longer at that version.
     #[inline]
-    pub fn delete(conn: &Database, section_id: i32) -> QueryResult<usize> {
-        let mut thread = Thread::find_id(conn, Section::find_id(conn, section_id)?.in_thread_id)?;
-        thread.sections_id.retain(|&cur_id| cur_id != section_id);
-        Thread::update(
-            conn,
-            thread.id,
-            &UpdateThread {
-                sections_id: thread.sections_id.into(),
-                ..Default::default()
-            },
-        )?;
+    pub fn delete(
+        conn: &Database,
+        section_id: i32,
+        expected_version: Option<i32>,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Result<usize, Rejection>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
 
-        let _ = Message {
-            room:      Room::Thread(thread.id),
-            action:    Action::Delete,
-            data_type: DataType::Section,
-            data:      &json!({ "id": section_id }),
+        transaction(conn, |deferred| {
+            // Lock the thread before the section, matching the order used when deleting a thread.
+            let thread_id = Self::find_id(conn, section_id)?.in_thread_id;
+            let mut thread = Thread::find_id_for_update(conn, thread_id)?;
+            let old = Self::find_id_for_update(conn, section_id)?;
+            if let Err(rejection) = old.may_change(expected_version, actor_id) {
+                return Ok(Err(rejection));
+            }
+
+            let position = thread
+                .sections_id
+                .iter()
+                .position(|&cur_id| cur_id == section_id);
+            thread.sections_id.retain(|&cur_id| cur_id != section_id);
+            Thread::update_deferred(
+                conn,
+                thread.id,
+                &UpdateThread {
+                    sections_id: thread.sections_id.into(),
+                    ..Default::default()
+                },
+                actor_id,
+                deferred,
+            )?;
+            Comment::remove_section(conn, thread.id, section_id, actor_id, deferred)?;
+            AuditLog::record(conn, actor_id, AuditAction::Delete, Some(&old), None)?;
+
+            deferred.push(move || {
+                CACHE.lock().remove(&section_id);
+            });
+            for &target in &[Room::Thread(thread.id), Room::Section(section_id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Delete,
+                    data_type: DataType::Section,
+                    data:      &json!({ "id": section_id }),
+                });
+            }
+
+            diesel::update(section)
+                .filter(id.eq(section_id))
+                .set((
+                    deleted_at_utc.eq(now),
+                    version.eq(version + 1),
+                    deleted_position.eq(position.map(|position| position as i32)),
+                ))
+                .execute(conn)
+                .map(Ok)
+        })
+    }
+
+    /// Undelete a `Section` given its ID,
+    /// re-inserting it into its thread at the position it was deleted from.
+    ///
+    /// The entry is added to the global cache and returned.
+    /// The undeletion is recorded in the audit log as performed by `actor_id`.
+    /// Returns `NotFound` if the section's thread has been deleted.
+    #[inline]
+    pub fn undelete(conn: &Database, section_id: i32, actor_id: Option<i32>) -> QueryResult<Self> {
+        let old = Self::find_id_including_deleted(conn, section_id)?;
+        if old.deleted_at_utc.is_none() {
+            return Ok(old);
         }
-        .send();
 
-        CACHE.lock().remove(&section_id);
-        diesel::delete(section)
-            .filter(id.eq(section_id))
+        transaction(conn, |deferred| {
+            let mut thread = Thread::find_id_for_update(conn, old.in_thread_id)?;
+
+            let result: Self = diesel::update(section)
+                .filter(id.eq(section_id))
+                .set((
+                    deleted_at_utc.eq(None::<i64>),
+                    deleted_position.eq(None::<i32>),
+                    version.eq(version + 1),
+                ))
+                .get_result(conn)?;
+            AuditLog::record(
+                conn,
+                actor_id,
+                AuditAction::Undelete,
+                Some(&old),
+                Some(&result),
+            )?;
+
+            let cached = result.clone();
+            deferred.push(move || {
+                CACHE.lock().insert(cached.id, cached);
+            });
+            for &target in &[Room::Thread(result.in_thread_id), Room::Section(result.id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Create,
+                    data_type: DataType::Section,
+                    data:      &result,
+                });
+            }
+
+            let position = old
+                .deleted_position
+                .map_or(thread.sections_id.len(), |position| {
+                    (position as usize).min(thread.sections_id.len())
+                });
+            thread.sections_id.insert(position, result.id);
+            Thread::update_deferred(
+                conn,
+                thread.id,
+                &UpdateThread {
+                    sections_id: thread.sections_id.into(),
+                    ..Default::default()
+                },
+                actor_id,
+                deferred,
+            )?;
+
+            Ok(result)
+        })
+    }
+
+    /// Delete all `Section`s belonging to a `Thread` as part of deleting the thread,
+    /// marking them as deleted at the same time as the thread.
+    ///
+    /// The thread's ordering is left untouched,
+    /// so that the sections can be undeleted along with the thread.
+    #[inline]
+    pub fn delete_by_thread(
+        conn: &Database,
+        thread_id: i32,
+        deleted_at: i64,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<usize> {
+        let values: Vec<Self> = section
+            .filter(in_thread_id.eq(thread_id))
+            .filter(deleted_at_utc.is_null())
+            .for_update()
+            .load(conn)?;
+
+        for value in values.iter() {
+            AuditLog::record(conn, actor_id, AuditAction::Delete, Some(value), None)?;
+
+            let value_id = value.id;
+            deferred.push(move || {
+                CACHE.lock().remove(&value_id);
+            });
+            for &target in &[Room::Thread(thread_id), Room::Section(value_id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Delete,
+                    data_type: DataType::Section,
+                    data:      &json!({ "id": value_id }),
+                });
+            }
+        }
+
+        diesel::update(section)
+            .filter(in_thread_id.eq(thread_id))
+            .filter(deleted_at_utc.is_null())
+            .set((deleted_at_utc.eq(deleted_at), version.eq(version + 1)))
             .execute(conn)
     }
+
+    /// Undelete all `Section`s that were deleted along with their `Thread`.
+    #[inline]
+    pub fn undelete_by_thread(
+        conn: &Database,
+        thread_id: i32,
+        deleted_at: i64,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<usize> {
+        let values: Vec<Self> = diesel::update(section)
+            .filter(in_thread_id.eq(thread_id))
+            .filter(deleted_at_utc.eq(deleted_at))
+            .filter(deleted_position.is_null())
+            .set((deleted_at_utc.eq(None::<i64>), version.eq(version + 1)))
+            .get_results(conn)?;
+
+        for value in values.iter() {
+            AuditLog::record(conn, actor_id, AuditAction::Undelete, None, Some(value))?;
+
+            for &target in &[Room::Thread(thread_id), Room::Section(value.id)] {
+                deferred.send(Message {
+                    room:      target,
+                    action:    Action::Create,
+                    data_type: DataType::Section,
+                    data:      value,
+                });
+            }
+        }
+
+        let count = values.len();
+        deferred.push(move || {
+            let mut cache = CACHE.lock();
+            for value in values {
+                cache.insert(value.id, value);
+            }
+        });
+
+        Ok(count)
+    }
 }
 
-impl ToMarkdown for Section {
-    /// Convert the `Section` object to valid markdown.
-    /// The resulting string is intended for consumption by Reddit,
-    /// but should be valid for any markdown flavor supporting tables.
+impl Section {
+    /// Convert the `Section` object to valid markdown,
+    /// leaving out the oldest `omitted_events` posted events.
+    ///
+    /// Has no effect on sections that are not an events section.
     #[inline]
-    fn to_markdown(&self, conn: &Database) -> Result<String, Box<dyn Error>> {
+    pub fn to_markdown_omitting_events(
+        &self,
+        conn: &Database,
+        omitted_events: usize,
+    ) -> Result<String, Box<dyn Error>> {
         let mut md = String::new();
 
         writeln!(&mut md, "# {}", self.name)?;
@@ -211,12 +740,12 @@ impl ToMarkdown for Section {
                     .join("|")
             )?;
 
-            for &event_id in thread.events_id.iter() {
-                write!(
-                    &mut md,
-                    "{}",
-                    Event::find_id(conn, event_id)?.to_markdown(conn)?
-                )?;
+            for event_md in self
+                .posted_events_markdown(conn)?
+                .iter()
+                .skip(omitted_events)
+            {
+                write!(&mut md, "{}", event_md)?;
             }
         } else {
             write!(&mut md, "{}", self.content)?;
@@ -224,4 +753,51 @@ impl ToMarkdown for Section {
 
         Ok(md)
     }
+
+    /// The markdown of each posted `Event` in the section's thread, oldest first.
+    /// Returns an empty `Vec` if the section is not an events section.
+    #[inline]
+    pub fn posted_events_markdown(&self, conn: &Database) -> Result<Vec<String>, Box<dyn Error>> {
+        if !self.is_events_section {
+            return Ok(vec![]);
+        }
+
+        let mut events = vec![];
+        for &event_id in Thread::find_id(conn, self.in_thread_id)?.events_id.iter() {
+            let md = Event::find_id(conn, event_id)?.to_markdown(conn)?;
+            if !md.is_empty() {
+                events.push(md);
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Markdown for the section with its content replaced by a short notice,
+    /// used when the thread is too long to include it.
+    #[inline]
+    pub fn to_collapsed_markdown(&self) -> String {
+        format!("# {}\n*Omitted due to length.*", self.name)
+    }
+}
+
+impl Audited for Section {
+    #[inline]
+    fn audit_entity(&self) -> AuditEntity {
+        AuditEntity {
+            data_type: DataType::Section,
+            id:        self.id,
+            thread_id: Some(self.in_thread_id),
+        }
+    }
+}
+
+impl ToMarkdown for Section {
+    /// Convert the `Section` object to valid markdown.
+    /// The resulting string is intended for consumption by Reddit,
+    /// but should be valid for any markdown flavor supporting tables.
+    #[inline]
+    fn to_markdown(&self, conn: &Database) -> Result<String, Box<dyn Error>> {
+        self.to_markdown_omitting_events(conn, 0)
+    }
 }
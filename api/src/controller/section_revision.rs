@@ -0,0 +1,75 @@
+use super::Section;
+use crate::{
+    schema::section_revision::{self, dsl::*},
+    Database,
+};
+use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The name and content of a `Section` at a point in time.
+///
+/// A revision is stored when the section is created
+/// and whenever either field is changed.
+/// `created_by_user_id` is `None` if the change was made by the API itself,
+/// or if the user has since been deleted.
+#[derive(Debug, Clone, Serialize, Queryable)]
+pub struct SectionRevision {
+    pub id: i32,
+    pub section_id: i32,
+    pub name: String,
+    pub content: String,
+    pub created_by_user_id: Option<i32>,
+    pub created_at_utc: i64,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "section_revision"]
+struct InsertSectionRevision<'a> {
+    section_id: i32,
+    name: &'a str,
+    content: &'a str,
+    created_by_user_id: Option<i32>,
+    created_at_utc: i64,
+}
+
+impl SectionRevision {
+    /// Find all revisions of a given `Section`, newest first.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// as revisions are only read on explicit request.
+    #[inline]
+    pub fn find_by_section(conn: &Database, for_section_id: i32) -> QueryResult<Vec<Self>> {
+        section_revision
+            .filter(section_id.eq(for_section_id))
+            .order(id.desc())
+            .load(conn)
+    }
+
+    /// Find a given `SectionRevision` by its ID.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_id(conn: &Database, revision_id: i32) -> QueryResult<Self> {
+        section_revision.find(revision_id).first(conn)
+    }
+
+    /// Store the current name and content of a `Section` as a new revision.
+    #[inline]
+    pub fn record(conn: &Database, value: &Section, actor_id: Option<i32>) -> QueryResult<Self> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        diesel::insert_into(section_revision)
+            .values(&InsertSectionRevision {
+                section_id: value.id,
+                name: &value.name,
+                content: &value.content,
+                created_by_user_id: actor_id,
+                created_at_utc: now,
+            })
+            .get_result(conn)
+    }
+}
@@ -1,6 +1,28 @@
 #![allow(non_snake_case)]
 
-use super::{Event, Section, ToMarkdown, User, THREAD_CACHE_SIZE};
+use super::{
+    overflow,
+    transaction,
+    AuditAction,
+    AuditEntity,
+    AuditLog,
+    Audited,
+    Comment,
+    Deferred,
+    Event,
+    Overflow,
+    OverflowStrategy,
+    RedditSync,
+    Rejection,
+    Rendered,
+    Section,
+    ToMarkdown,
+    User,
+    CONTINUATION_NOTICE,
+    OVERFLOW_STRATEGY,
+    SELFTEXT_LIMIT,
+    THREAD_CACHE_SIZE,
+};
 use crate::{
     schema::thread::{self, dsl::*},
     websocket::*,
@@ -13,7 +35,12 @@ use parking_lot::Mutex;
 use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
 use serde::Deserialize;
 use serde_json::{json, value::Value as Json};
-use std::{error::Error, fmt::Write};
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 lazy_static! {
     /// A global cache, containing a mapping of IDs to their respective `Event`.
@@ -46,6 +73,10 @@ generate_structs! {
         event_column_headers: Vec<String>,
         readonly space__utc_col_index: Option<i16>,
         is_live: bool = false,
+        flair_id: Option<String>,
+        flair_text: Option<String>,
+        auto deleted_at_utc: Option<i64>,
+        auto version: i32,
     }
 }
 
@@ -63,15 +94,34 @@ pub struct ExternalInsertThread {
     pub event_column_headers: Vec<String>,
     pub space__utc_col_index: Option<i16>,
     pub is_live: Option<bool>,
+    pub flair_id: Option<String>,
+    pub flair_text: Option<String>,
+}
+
+/// Whether the proposed ordering contains exactly the IDs currently present,
+/// or no new ordering is proposed at all.
+#[inline]
+fn is_reordering(current: &[i32], proposed: Option<&Vec<i32>>) -> bool {
+    proposed.map_or(true, |proposed| {
+        current.iter().collect::<BTreeSet<_>>() == proposed.iter().collect()
+    })
 }
 
 impl Thread {
-    /// Find all `Thread`s in the database.
+    /// Find all `Thread`s in the database that have not been deleted.
     ///
     /// Does _not_ use cache (reading or writing),
     /// so as to avoid storing values rarely accessed.
     #[inline]
     pub fn find_all(conn: &Database) -> QueryResult<Vec<Self>> {
+        thread.filter(deleted_at_utc.is_null()).load(conn)
+    }
+
+    /// Find all `Thread`s in the database, including those that have been deleted.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_all_including_deleted(conn: &Database) -> QueryResult<Vec<Self>> {
         thread.load(conn)
     }
 
@@ -86,7 +136,7 @@ impl Thread {
     #[inline]
     pub fn find_id_with_foreign_keys(conn: &Database, thread_id: i32) -> QueryResult<Json> {
         // Get the values, represented as normal structs.
-        // For sections, we also add the relation to `User`,
+        // For sections and events, we also add the relation to `User`,
         // so we represent those as raw, untyped JSON values.
         let raw_thread = Thread::find_id(conn, thread_id)?;
         let created_by_user = User::find_id(conn, raw_thread.created_by_user_id)?;
@@ -107,6 +157,14 @@ impl Thread {
             .events_id
             .iter()
             .map(|event_id| Event::find_id(conn, *event_id).unwrap())
+            .map(|event| {
+                let user_id = event.lock_held_by_user_id;
+                let mut event = serde_json::to_value(event).unwrap();
+                event["lock_held_by_user"] = user_id.map_or(json!(null), |user_id| {
+                    serde_json::to_value(User::find_id(conn, user_id).unwrap()).unwrap()
+                });
+                event
+            })
             .collect();
 
         // Convert the values to JSON,
@@ -121,26 +179,242 @@ impl Thread {
     /// Update a `Thread` on Reddit.
     ///
     /// This method will return `Ok(())` if the thread is not posted on Reddit.
+    /// Nothing is sent for the post or any of its managed comments
+    /// if the markdown is identical to what was last pushed.
+    ///
+    /// Performs a blocking request to Reddit;
+    /// endpoints should use `reddit_queue::enqueue` instead.
     #[inline]
-    pub fn update_on_reddit(&self, conn: &Database) -> QueryResult<()> {
-        if self.post_id.is_none() {
-            return Ok(());
-        }
+    pub fn update_on_reddit(&self, conn: &Database) -> Result<(), Box<dyn Error>> {
+        let reddit_id = match &self.post_id {
+            Some(reddit_id) => reddit_id,
+            None => return Ok(()),
+        };
 
+        let rendered = self.render(conn)?;
         let mut user: reddit::User<'_> = User::find_id(conn, self.created_by_user_id)?.into();
 
-        user.edit_self_post(
-            &format!("t3_{}", self.post_id.clone().unwrap()),
-            &self.to_markdown(conn).unwrap(),
-        )
-        .expect("error updating post on Reddit");
+        if !self.is_synced_with(conn, &rendered.markdown)? {
+            user.edit_self_post(&format!("t3_{}", reddit_id), &rendered.markdown)?;
+            RedditSync::record(conn, self.id, &rendered.markdown)?;
+        }
 
-        User::update_access_token_if_necessary(conn, self.created_by_user_id, &mut user)
-            .expect("could not update access token");
+        // Keep the managed comments in sync as well,
+        // including any sections that didn't fit in the post itself.
+        let continued_sections_id = match rendered.overflow {
+            Some(Overflow::Continuation {
+                continued_sections_id,
+            }) => continued_sections_id,
+            _ => vec![],
+        };
+        Comment::set_continuation(conn, self.id, continued_sections_id)?;
+        for comment in Comment::find_by_thread(conn, self.id)? {
+            comment.update_on_reddit(conn, &mut user, reddit_id)?;
+        }
+
+        User::update_access_token_if_necessary(conn, self.created_by_user_id, &mut user)?;
 
         Ok(())
     }
 
+    /// Render the `Thread` to markdown that fits within Reddit's length limit,
+    /// applying the configured `OverflowStrategy` if necessary.
+    #[inline]
+    pub fn render(&self, conn: &Database) -> Result<Rendered, Box<dyn Error>> {
+        self.render_within(conn, *SELFTEXT_LIMIT, *OVERFLOW_STRATEGY)
+    }
+
+    /// Render the `Thread` to markdown no longer than `limit` characters,
+    /// applying the provided `OverflowStrategy` if necessary.
+    #[inline]
+    pub fn render_within(
+        &self,
+        conn: &Database,
+        limit: usize,
+        strategy: OverflowStrategy,
+    ) -> Result<Rendered, Box<dyn Error>> {
+        let sections = self
+            .sections_id
+            .iter()
+            .map(|&section_id| Section::find_id(conn, section_id))
+            .collect::<QueryResult<Vec<_>>>()?;
+        let join =
+            |mds: &[String]| -> String { mds.iter().map(|md| format!("{}\n\n", md)).collect() };
+
+        let mut mds = sections
+            .iter()
+            .map(|section| section.to_markdown(conn))
+            .collect::<Result<Vec<_>, _>>()?;
+        let md: String = join(&mds);
+
+        if overflow::fits(&md, limit) {
+            return Ok(Rendered {
+                markdown: md,
+                overflow: None,
+            });
+        }
+
+        let shortened = match strategy {
+            OverflowStrategy::TruncateEvents => {
+                let mut posted_events = 0;
+                for section in sections.iter() {
+                    posted_events = posted_events.max(section.posted_events_markdown(conn)?.len());
+                }
+                let render_omitting = |omitted_events| -> Result<String, Box<dyn Error>> {
+                    Ok(join(
+                        &sections
+                            .iter()
+                            .map(|section| {
+                                section.to_markdown_omitting_events(conn, omitted_events)
+                            })
+                            .collect::<Result<Vec<_>, _>>()?,
+                    ))
+                };
+
+                // Length decreases as more events are omitted,
+                // so search for the fewest omissions that fit.
+                let (mut low, mut high) = (1, posted_events);
+                while low < high {
+                    let mid = (low + high) / 2;
+                    if overflow::fits(&render_omitting(mid)?, limit) {
+                        high = mid;
+                    } else {
+                        low = mid + 1;
+                    }
+                }
+
+                let md = render_omitting(low)?;
+                match overflow::fits(&md, limit) {
+                    true => Some((
+                        md,
+                        Overflow::TruncateEvents {
+                            omitted_events: low,
+                        },
+                    )),
+                    false => None,
+                }
+            }
+            OverflowStrategy::CollapseSections => {
+                let mut order: Vec<_> = (0..sections.len()).collect();
+                order.sort_by_key(|&i| std::cmp::Reverse(mds[i].chars().count()));
+
+                let mut collapsed_sections_id = vec![];
+                let mut collapsed_md = md.clone();
+                for i in order {
+                    mds[i] = sections[i].to_collapsed_markdown();
+                    collapsed_sections_id.push(sections[i].id);
+                    collapsed_md = join(&mds);
+
+                    if overflow::fits(&collapsed_md, limit) {
+                        break;
+                    }
+                }
+
+                match overflow::fits(&collapsed_md, limit) {
+                    true => Some((
+                        collapsed_md,
+                        Overflow::CollapseSections {
+                            collapsed_sections_id,
+                        },
+                    )),
+                    false => None,
+                }
+            }
+            OverflowStrategy::Continuation => {
+                let mut kept = vec![];
+                let mut continued_sections_id = vec![];
+
+                // Keep sections in the post until one doesn't fit,
+                // moving that section and all following into the comment.
+                for (section, section_md) in sections.iter().zip(mds.iter()) {
+                    let mut candidate = kept.clone();
+                    candidate.push(section_md.clone());
+                    candidate.push(CONTINUATION_NOTICE.to_owned());
+
+                    if continued_sections_id.is_empty() && overflow::fits(&join(&candidate), limit)
+                    {
+                        kept.push(section_md.clone());
+                    } else {
+                        continued_sections_id.push(section.id);
+                    }
+                }
+
+                kept.push(CONTINUATION_NOTICE.to_owned());
+                let continued_md = join(&kept);
+
+                match overflow::fits(&continued_md, limit) {
+                    true => Some((
+                        continued_md,
+                        Overflow::Continuation {
+                            continued_sections_id,
+                        },
+                    )),
+                    false => None,
+                }
+            }
+        };
+
+        Ok(match shortened {
+            Some((markdown, overflow)) => Rendered {
+                markdown,
+                overflow: Some(overflow),
+            },
+            None => Rendered {
+                markdown: overflow::truncate(&md, limit),
+                overflow: Some(Overflow::Truncate),
+            },
+        })
+    }
+
+    /// Set the flair of a post on Reddit,
+    /// returning the ID of the flair template that was applied (if any).
+    ///
+    /// An explicit template ID is used as-is, along with any text provided.
+    /// Otherwise, the template with text matching `text` (ignoring case) is used,
+    /// so that passing `"Launch"` selects the subreddit's launch flair.
+    /// If no template matches, the text is applied on its own,
+    /// which Reddit only permits moderators to do.
+    #[inline]
+    pub fn set_flair_on_reddit(
+        user: &mut reddit::User<'_>,
+        subreddit_name: &str,
+        reddit_id: &str,
+        template_id: Option<&str>,
+        text: Option<&str>,
+    ) -> reddit::Result<Option<String>> {
+        let (template_id, text) = match (template_id, text) {
+            (Some(template_id), text) => (Some(template_id.to_owned()), text),
+            (None, Some(text)) => {
+                let template_id = user
+                    .link_flairs(subreddit_name)?
+                    .into_iter()
+                    .find(|flair| flair.text.eq_ignore_ascii_case(text))
+                    .map(|flair| flair.id);
+
+                match template_id {
+                    Some(template_id) => (Some(template_id), None),
+                    None => (None, Some(text)),
+                }
+            }
+            (None, None) => return Ok(None),
+        };
+
+        user.set_link_flair(
+            subreddit_name,
+            &format!("t3_{}", reddit_id),
+            template_id.as_ref().map(String::as_str),
+            text,
+        )?;
+
+        Ok(template_id)
+    }
+
+    /// Is the provided markdown identical to what was last pushed to Reddit?
+    #[inline]
+    pub fn is_synced_with(&self, conn: &Database, md: &str) -> QueryResult<bool> {
+        Ok(RedditSync::find_id(conn, self.id)?.map_or(false, |sync| sync.markdown == md))
+    }
+
     /// Find a given `Thread` by its ID.
     ///
     /// Internally uses a cache to limit database accesses.
@@ -150,12 +424,38 @@ impl Thread {
         if cache.contains_key(&thread_id) {
             Ok(cache.get_mut(&thread_id).unwrap().clone())
         } else {
-            let result: Self = thread.find(thread_id).first(conn)?;
+            let result: Self = thread
+                .find(thread_id)
+                .filter(deleted_at_utc.is_null())
+                .first(conn)?;
             cache.insert(thread_id, result.clone());
             Ok(result)
         }
     }
 
+    /// Find a given `Thread` by its ID, locking its row until the end of the transaction.
+    /// Returns `NotFound` if it has been deleted.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// as the value must reflect any concurrent changes to the ordering of the thread.
+    #[inline]
+    pub fn find_id_for_update(conn: &Database, thread_id: i32) -> QueryResult<Self> {
+        thread
+            .find(thread_id)
+            .filter(deleted_at_utc.is_null())
+            .for_update()
+            .first(conn)
+    }
+
+    /// Find a given `Thread` by its ID, even if it has been deleted.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// as deleted threads are never cached.
+    #[inline]
+    pub fn find_id_including_deleted(conn: &Database, thread_id: i32) -> QueryResult<Self> {
+        thread.find(thread_id).first(conn)
+    }
+
     /// Create a `Thread` given the data.
     ///
     /// The inserted row is added to the global cache and returned.
@@ -180,12 +480,21 @@ impl Thread {
             event_column_headers: data.event_column_headers.clone(),
             space__utc_col_index: data.space__utc_col_index,
             is_live: data.is_live.unwrap_or(false),
+            flair_id: data.flair_id.clone(),
+            flair_text: data.flair_text.clone(),
         };
 
         let result: Self = diesel::insert_into(thread)
             .values(insertable_thread)
             .get_result(conn)?;
         CACHE.lock().insert(result.id, result.clone());
+        AuditLog::record(
+            conn,
+            Some(user_id),
+            AuditAction::Create,
+            None,
+            Some(&result),
+        )?;
 
         let _ = Message {
             room:      Room::ThreadCreate,
@@ -201,43 +510,177 @@ impl Thread {
     /// Update a `Thread` given an ID and the data to update.
     ///
     /// The entry is updated in the database, added to cache, and returned.
+    /// The change is recorded in the audit log as performed by `actor_id`.
+    ///
+    /// If `expected_version` is provided,
+    /// no changes are made if the thread is no longer at that version.
+    /// The sections and events may only be reordered, not added or removed;
+    /// that is done by creating and deleting them.
+    #[inline]
+    pub fn update(
+        conn: &Database,
+        thread_id: i32,
+        expected_version: Option<i32>,
+        data: &UpdateThread,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Result<Self, Rejection>> {
+        transaction(conn, |deferred| {
+            let current = Self::find_id_for_update(conn, thread_id)?;
+            if expected_version.map_or(false, |expected| expected != current.version) {
+                return Ok(Err(Rejection::VersionMismatch));
+            }
+            if !is_reordering(&current.sections_id, data.sections_id.as_ref())
+                || !is_reordering(&current.events_id, data.events_id.as_ref())
+            {
+                return Ok(Err(Rejection::OrderingMismatch));
+            }
+
+            Self::update_deferred(conn, thread_id, data, actor_id, deferred).map(Ok)
+        })
+    }
+
+    /// Update a `Thread` as part of a larger transaction,
+    /// incrementing its version.
+    ///
+    /// Writing the entry to cache and notifying clients
+    /// are deferred until the transaction has been committed.
     #[inline]
-    pub fn update(conn: &Database, thread_id: i32, data: &UpdateThread) -> QueryResult<Self> {
+    pub fn update_deferred(
+        conn: &Database,
+        thread_id: i32,
+        data: &UpdateThread,
+        actor_id: Option<i32>,
+        deferred: &mut Deferred,
+    ) -> QueryResult<Self> {
+        let old = Self::find_id(conn, thread_id)?;
         let result: Self = diesel::update(thread)
             .filter(id.eq(thread_id))
-            .set(data)
+            .set((data, version.eq(version + 1)))
             .get_result(conn)?;
-        CACHE.lock().insert(result.id, result.clone());
+        AuditLog::record(
+            conn,
+            actor_id,
+            AuditAction::Update,
+            Some(&old),
+            Some(&result),
+        )?;
 
-        let _ = Message {
+        let cached = result.clone();
+        deferred.push(move || {
+            CACHE.lock().insert(cached.id, cached);
+        });
+        deferred.send(Message {
             room:      Room::Thread(thread_id),
             action:    Action::Update,
             data_type: DataType::Thread,
             data:      &Update::new(thread_id, data),
-        }
-        .send();
+        });
 
         Ok(result)
     }
 
     /// Delete a `Thread` given its ID.
     ///
-    /// Removes the entry from cache and returns the number of rows deleted (should be `1`).
+    /// The thread is only marked as deleted, so that it can later be undeleted.
+    /// Its sections and events are deleted along with it in a single transaction.
+    /// Removes the entries from cache and returns the number of threads deleted (should be `1`).
+    /// The deletion is recorded in the audit log as performed by `actor_id`.
+    ///
+    /// If `expected_version` is provided,
+    /// `None` is returned without making any changes if the thread is no longer at that version.
     #[inline]
-    pub fn delete(conn: &Database, thread_id: i32) -> QueryResult<usize> {
-        CACHE.lock().remove(&thread_id);
+    pub fn delete(
+        conn: &Database,
+        thread_id: i32,
+        expected_version: Option<i32>,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Option<usize>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
 
-        let _ = Message {
-            room:      Room::Thread(thread_id),
-            action:    Action::Delete,
+        transaction(conn, |deferred| {
+            let old = Self::find_id_for_update(conn, thread_id)?;
+            if expected_version.map_or(false, |expected| expected != old.version) {
+                return Ok(None);
+            }
+
+            Section::delete_by_thread(conn, thread_id, now, actor_id, deferred)?;
+            Event::delete_by_thread(conn, thread_id, now, actor_id, deferred)?;
+            AuditLog::record(conn, actor_id, AuditAction::Delete, Some(&old), None)?;
+
+            deferred.push(move || {
+                CACHE.lock().remove(&thread_id);
+            });
+            deferred.send(Message {
+                room:      Room::Thread(thread_id),
+                action:    Action::Delete,
+                data_type: DataType::Thread,
+                data:      &json!({ "id": thread_id }),
+            });
+
+            diesel::update(thread)
+                .filter(id.eq(thread_id))
+                .set((deleted_at_utc.eq(now), version.eq(version + 1)))
+                .execute(conn)
+                .map(Some)
+        })
+    }
+
+    /// Undelete a `Thread` given its ID,
+    /// along with the sections and events that were deleted with it.
+    ///
+    /// The entry is added to the global cache and returned.
+    /// The undeletion is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn undelete(conn: &Database, thread_id: i32, actor_id: Option<i32>) -> QueryResult<Self> {
+        let old = Self::find_id_including_deleted(conn, thread_id)?;
+        let deleted_at = match old.deleted_at_utc {
+            Some(deleted_at) => deleted_at,
+            None => return Ok(old),
+        };
+
+        transaction(conn, |deferred| {
+            let result: Self = diesel::update(thread)
+                .filter(id.eq(thread_id))
+                .set((deleted_at_utc.eq(None::<i64>), version.eq(version + 1)))
+                .get_result(conn)?;
+            AuditLog::record(
+                conn,
+                actor_id,
+                AuditAction::Undelete,
+                Some(&old),
+                Some(&result),
+            )?;
+
+            let cached = result.clone();
+            deferred.push(move || {
+                CACHE.lock().insert(cached.id, cached);
+            });
+            deferred.send(Message {
+                room:      Room::ThreadCreate,
+                action:    Action::Create,
+                data_type: DataType::Thread,
+                data:      &result,
+            });
+
+            Section::undelete_by_thread(conn, thread_id, deleted_at, actor_id, deferred)?;
+            Event::undelete_by_thread(conn, thread_id, deleted_at, actor_id, deferred)?;
+
+            Ok(result)
+        })
+    }
+}
+
+impl Audited for Thread {
+    #[inline]
+    fn audit_entity(&self) -> AuditEntity {
+        AuditEntity {
             data_type: DataType::Thread,
-            data:      &json!({ "id": thread_id }),
+            id:        self.id,
+            thread_id: Some(self.id),
         }
-        .send();
-
-        diesel::delete(thread)
-            .filter(id.eq(thread_id))
-            .execute(conn)
     }
 }
 
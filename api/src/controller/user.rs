@@ -1,6 +1,18 @@
 #![allow(non_snake_case)]
 
-use super::{Claim, Thread, USER_CACHE_SIZE};
+use super::{
+    transaction,
+    AuditAction,
+    AuditEntity,
+    AuditLog,
+    Audited,
+    Claim,
+    Deferred,
+    Role,
+    Thread,
+    UserSubredditRole,
+    USER_CACHE_SIZE,
+};
 use crate::{
     encryption::{decrypt, encrypt},
     endpoint::oauth::REDDIT,
@@ -19,9 +31,13 @@ use rocket::{
     Outcome,
 };
 use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
-use std::time::{Duration, UNIX_EPOCH};
+use serde::Deserialize;
+use std::{
+    error::Error,
+    time::{Duration, UNIX_EPOCH},
+};
 #[cfg(debug_assertions)]
-use {rocket_contrib::json::Json, serde::Deserialize, serde_json::json};
+use {rocket_contrib::json::Json, serde_json::json};
 
 lazy_static! {
     /// A global cache, containing a mapping of IDs to their respective `Event`.
@@ -45,14 +61,22 @@ generate_structs! {
         lang: String = "en",
         private refresh_token: Vec<u8>,
         is_global_admin: bool = false,
-        spacex__is_host: bool = false,
-        spacex__is_mod: bool = false,
         spacex__is_slack_member: bool = false,
         private access_token: Vec<u8>,
         private access_token_expires_at_utc: i64,
+        needs_reauth: bool = false,
     }
 }
 
+/// The role flags of a `User` that global admins are able to change.
+/// Roles in specific subreddits are managed via `UserSubredditRole`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateUserRoles {
+    pub is_global_admin:         Option<bool>,
+    pub spacex__is_slack_member: Option<bool>,
+}
+
 // TODO make these macros!
 
 /// This struct is necessary to perform the requisite encryption
@@ -65,8 +89,6 @@ pub struct ExternalUpdateUser {
     pub lang: Option<String>,
     pub refresh_token: Option<String>,
     pub is_global_admin: Option<bool>,
-    pub spacex__is_host: Option<bool>,
-    pub spacex__is_mod: Option<bool>,
     pub spacex__is_slack_member: Option<bool>,
     pub access_token: Option<String>,
     pub access_token_expires_at_utc: Option<i64>,
@@ -85,11 +107,10 @@ impl Into<UpdateUser> for Json<ExternalUpdateUser> {
             lang: self.lang.clone(),
             refresh_token: self.refresh_token.clone().map(|s| encrypt(&s)),
             is_global_admin: self.is_global_admin,
-            spacex__is_host: self.spacex__is_host,
-            spacex__is_mod: self.spacex__is_mod,
             spacex__is_slack_member: self.spacex__is_slack_member,
             access_token: self.access_token.clone().map(|s| encrypt(&s)),
             access_token_expires_at_utc: self.access_token_expires_at_utc,
+            needs_reauth: None,
         }
     }
 }
@@ -122,10 +143,6 @@ pub struct ExternalInsertUser {
     #[serde(default = "falsey")]
     pub is_global_admin: bool,
     #[serde(default = "falsey")]
-    pub spacex__is_host: bool,
-    #[serde(default = "falsey")]
-    pub spacex__is_mod: bool,
-    #[serde(default = "falsey")]
     pub spacex__is_slack_member: bool,
     pub access_token: String,
     pub access_token_expires_at_utc: i64,
@@ -145,11 +162,10 @@ impl Into<InsertUser> for Json<ExternalInsertUser> {
             lang: self.lang.clone(),
             refresh_token: encrypt(&self.refresh_token),
             is_global_admin: self.is_global_admin,
-            spacex__is_host: self.spacex__is_host,
-            spacex__is_mod: self.spacex__is_mod,
             spacex__is_slack_member: self.spacex__is_slack_member,
             access_token: encrypt(&self.access_token),
             access_token_expires_at_utc: self.access_token_expires_at_utc,
+            needs_reauth: false,
         }
     }
 }
@@ -159,21 +175,27 @@ impl User {
     ///
     /// If the subreddit is not known, returns `false`.
     #[inline]
-    pub fn is_moderator_of(&self, subreddit: Option<&str>) -> bool {
-        match subreddit {
-            Some("spacex") => self.spacex__is_mod,
-            _ => false,
-        }
+    pub fn is_moderator_of(&self, conn: &Database, subreddit: Option<&str>) -> bool {
+        self.has_role(conn, subreddit, Role::Moderator)
     }
 
     /// Check if the user is a host of a given subreddit.
     ///
     /// If the subreddit is not known, returns `false`.
     #[inline]
-    pub fn is_host_for(&self, subreddit: Option<&str>) -> bool {
+    pub fn is_host_for(&self, conn: &Database, subreddit: Option<&str>) -> bool {
+        self.has_role(conn, subreddit, Role::Host)
+    }
+
+    /// Check if the user holds a role in a given subreddit.
+    ///
+    /// If the subreddit is not known, returns `false`.
+    #[inline]
+    fn has_role(&self, conn: &Database, subreddit: Option<&str>, expected_role: Role) -> bool {
         match subreddit {
-            Some("spacex") => self.spacex__is_host,
-            _ => false,
+            Some(subreddit) => UserSubredditRole::has_role(conn, self.id, subreddit, expected_role)
+                .unwrap_or(false),
+            None => false,
         }
     }
 
@@ -207,8 +229,23 @@ impl User {
             thread.unwrap()
         };
 
+        self.can_modify(conn, &thread)
+    }
+
+    /// Is the provided user able to modify the given thread?
+    ///
+    /// Identical to `can_modify_thread`,
+    /// but accepts a thread that has already been found,
+    /// such as one that has been deleted.
+    #[inline]
+    pub fn can_modify(&self, conn: &Database, thread: &Thread) -> bool {
+        // Global admins can change anything.
+        if self.is_global_admin {
+            return true;
+        }
+
         // The user is a host in a given subreddit.
-        if self.is_host_for(thread.subreddit.as_ref().map(String::as_str)) {
+        if self.is_host_for(conn, thread.subreddit.as_ref().map(String::as_str)) {
             return true;
         }
 
@@ -216,6 +253,71 @@ impl User {
         thread.created_by_user_id == self.id
     }
 
+    /// Fetch the subreddits the user moderates from Reddit,
+    /// updating their moderator roles to match.
+    ///
+    /// Tokens issued before we requested the necessary scope can't be used to do this.
+    /// In that case, the user is flagged as needing to authenticate with Reddit again.
+    ///
+    /// Returns all roles held by the user afterwards.
+    #[inline]
+    pub fn refresh_moderator_status(
+        conn: &Database,
+        user_id: i32,
+    ) -> Result<Vec<UserSubredditRole>, Box<dyn Error>> {
+        let mut reddit_user: reddit::User<'_> = Self::find_id(conn, user_id)?.into();
+        let moderated = Self::fetch_moderated_subreddits(&mut reddit_user);
+        Self::set_moderated_subreddits(conn, user_id, &mut reddit_user, moderated)
+    }
+
+    /// Fetch the subreddits the user moderates from Reddit.
+    ///
+    /// The database isn't touched,
+    /// so no connection needs to be held while waiting on Reddit.
+    #[inline]
+    pub fn fetch_moderated_subreddits(
+        reddit_user: &mut reddit::User<'_>,
+    ) -> Result<Vec<String>, reddit::Error> {
+        // Avoid listing the moderated subreddits when the user doesn't moderate any.
+        match reddit_user.is_moderator()? {
+            true => reddit_user.moderated_subreddits(),
+            false => Ok(vec![]),
+        }
+    }
+
+    /// Update the user's moderator roles to match the result of `fetch_moderated_subreddits`,
+    /// storing any access token that was refreshed along the way.
+    ///
+    /// Returns all roles held by the user afterwards.
+    #[inline]
+    pub fn set_moderated_subreddits(
+        conn: &Database,
+        user_id: i32,
+        reddit_user: &mut reddit::User<'_>,
+        moderated: Result<Vec<String>, reddit::Error>,
+    ) -> Result<Vec<UserSubredditRole>, Box<dyn Error>> {
+        Self::update_access_token_if_necessary(conn, user_id, reddit_user)?;
+
+        let moderated = match moderated {
+            Ok(moderated) => moderated,
+            Err(err @ reddit::Error::InsufficientScope) => {
+                Self::update(
+                    conn,
+                    user_id,
+                    &UpdateUser {
+                        needs_reauth: true.into(),
+                        ..UpdateUser::default()
+                    },
+                )?;
+                return Err(err.into());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        UserSubredditRole::set_subreddits(conn, user_id, Role::Moderator, &moderated)
+            .map_err(Into::into)
+    }
+
     /// When performing any request to Reddit,
     /// we need to send an access token to authenticate ourselves.
     /// These tokens must be refreshed every hour (currently; that's subject to change).
@@ -230,7 +332,7 @@ impl User {
         conn: &Database,
         user_id: i32,
         reddit_user: &mut reddit::User<'_>,
-    ) -> QueryResult<Self> {
+    ) -> Result<Self, Box<dyn Error>> {
         let db_user = Self::find_id(conn, user_id)?;
         let current_expires_at = db_user.access_token_expires_at_utc;
         let new_expires_at = reddit_user
@@ -244,16 +346,76 @@ impl User {
                 conn,
                 user_id,
                 &UpdateUser {
-                    access_token: encrypt(reddit_user.access_token().as_ref()).into(),
+                    access_token: encrypt(reddit_user.access_token()?).into(),
                     access_token_expires_at_utc: new_expires_at.into(),
                     ..UpdateUser::default()
                 },
             )
+            .map_err(Into::into)
         } else {
             Ok(db_user)
         }
     }
 
+    /// Change the role flags of a `User`.
+    ///
+    /// Returns `None` without making any changes
+    /// if doing so would leave no global admins.
+    /// The global admins are locked until the change is complete,
+    /// so two admins can't simultaneously demote each other.
+    /// The change is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn update_roles(
+        conn: &Database,
+        user_id: i32,
+        data: &UpdateUserRoles,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Option<Self>> {
+        // There's nothing to update.
+        if data.is_global_admin.is_none() && data.spacex__is_slack_member.is_none() {
+            return Self::find_id(conn, user_id).map(Some);
+        }
+
+        transaction(conn, |deferred| {
+            if data.is_global_admin == Some(false) {
+                let admins: Vec<i32> = user
+                    .select(id)
+                    .filter(is_global_admin.eq(true))
+                    .for_update()
+                    .load(conn)?;
+
+                if admins == [user_id] {
+                    return Ok(None);
+                }
+            }
+
+            let old = Self::find_id(conn, user_id)?;
+            let result = Self::update_deferred(
+                conn,
+                user_id,
+                &UpdateUser {
+                    is_global_admin: data.is_global_admin,
+                    spacex__is_slack_member: data.spacex__is_slack_member,
+                    ..UpdateUser::default()
+                },
+                deferred,
+            )?;
+            AuditLog::record(
+                conn,
+                actor_id,
+                AuditAction::Update,
+                Some(&old),
+                Some(&result),
+            )?;
+
+            // A demoted admin must stop receiving changes to users.
+            let updated = result.clone();
+            deferred.push(move || evict(&updated));
+
+            Ok(Some(result))
+        })
+    }
+
     /// Find all `User`s in the database.
     ///
     /// Does _not_ use cache (reading or writing),
@@ -263,6 +425,17 @@ impl User {
         user.load(conn)
     }
 
+    /// Find a specific `User` given their Reddit username.
+    ///
+    /// Does _not_ use cache for reading,
+    /// as it is keyed by ID.
+    #[inline]
+    pub fn find_by_reddit_username(conn: &Database, username: &str) -> QueryResult<Self> {
+        let result: Self = user.filter(reddit_username.eq(username)).first(conn)?;
+        CACHE.lock().insert(result.id, result.clone());
+        Ok(result)
+    }
+
     /// Find a specific `User` given its ID.
     ///
     /// Internally uses a cache to limit database accesses.
@@ -302,19 +475,37 @@ impl User {
     /// The entry is updated in the database, added to cache, and returned.
     #[inline]
     pub fn update(conn: &Database, user_id: i32, data: &UpdateUser) -> QueryResult<Self> {
+        transaction(conn, |deferred| {
+            Self::update_deferred(conn, user_id, data, deferred)
+        })
+    }
+
+    /// Update a `User` as part of a larger transaction.
+    ///
+    /// Writing the entry to cache and notifying clients
+    /// are deferred until the transaction has been committed.
+    #[inline]
+    fn update_deferred(
+        conn: &Database,
+        user_id: i32,
+        data: &UpdateUser,
+        deferred: &mut Deferred,
+    ) -> QueryResult<Self> {
         let result: Self = diesel::update(user)
             .filter(id.eq(user_id))
             .set(data)
             .get_result(conn)?;
-        CACHE.lock().insert(result.id, result.clone());
 
-        let _ = Message {
+        let cached = result.clone();
+        deferred.push(move || {
+            CACHE.lock().insert(cached.id, cached);
+        });
+        deferred.send(Message {
             room:      Room::User,
             action:    Action::Update,
             data_type: DataType::User,
             data:      &Update::new(user_id, data),
-        }
-        .send();
+        });
 
         Ok(result)
     }
@@ -339,6 +530,17 @@ impl User {
     }
 }
 
+impl Audited for User {
+    #[inline]
+    fn audit_entity(&self) -> AuditEntity {
+        AuditEntity {
+            data_type: DataType::User,
+            id:        self.id,
+            thread_id: None,
+        }
+    }
+}
+
 impl<'a, 'r> FromRequest<'a, 'r> for User {
     type Error = &'a str;
 
@@ -0,0 +1,296 @@
+use super::{AuditAction, AuditEntity, AuditLog, Audited, USER_SUBREDDIT_ROLE_CACHE_SIZE};
+use crate::{
+    schema::user_subreddit_role::{self, dsl::*},
+    websocket::*,
+    Database,
+};
+use derive_more::Display;
+use diesel::dsl::exists;
+use enceladus_macros::generate_structs;
+use lazy_static::lazy_static;
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use rocket_contrib::databases::diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+lazy_static! {
+    /// A global cache, containing a mapping of IDs to their respective `UserSubredditRole`.
+    ///
+    /// The cache is protected by a `Mutex`,
+    /// ensuring there is only ever at most one writer at a time.
+    /// Note that even when reading,
+    /// there must be a lock on mutability,
+    /// as the `LruCache` must be able to update itself.
+    ///
+    /// To read from the cache,
+    /// you'll want to call `CACHE.lock()` before performing normal operations.
+    static ref CACHE: Mutex<LruCache<i32, UserSubredditRole>> =
+        Mutex::new(LruCache::new(USER_SUBREDDIT_ROLE_CACHE_SIZE));
+}
+
+/// A role a `User` may hold in a given subreddit.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Able to modify any thread in the subreddit,
+    /// in the same manner as the thread's author.
+    #[display(fmt = "host")]
+    Host,
+
+    /// A moderator of the subreddit on Reddit.
+    /// Able to approve and sticky threads and comments.
+    #[display(fmt = "moderator")]
+    Moderator,
+}
+
+// Subreddits are stored in lowercase,
+// as Reddit treats their names case-insensitively.
+// Roles synced from Reddit are flagged,
+// so that those granted by global admins aren't removed by the sync.
+generate_structs! {
+    UserSubredditRole("user_subreddit_role") {
+        auto id: i32,
+        readonly user_id: i32,
+        readonly subreddit: String,
+        role: String,
+        readonly is_from_reddit: bool = false,
+    }
+}
+
+// The role must be one of the known values,
+// so use an `ExternalInsertUserSubredditRole` wherever user input is expected.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalInsertUserSubredditRole {
+    pub user_id:   i32,
+    pub subreddit: String,
+    pub role:      Role,
+}
+
+// The same applies when updating.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalUpdateUserSubredditRole {
+    pub role: Option<Role>,
+}
+
+impl UserSubredditRole {
+    /// Find all `UserSubredditRole`s in the database.
+    ///
+    /// Does _not_ use cache (reading or writing),
+    /// so as to avoid storing values rarely accessed.
+    #[inline]
+    pub fn find_all(conn: &Database) -> QueryResult<Vec<Self>> {
+        user_subreddit_role.load(conn)
+    }
+
+    /// Find all `UserSubredditRole`s held by a given `User`.
+    ///
+    /// Does _not_ use cache (reading or writing).
+    #[inline]
+    pub fn find_by_user(conn: &Database, for_user_id: i32) -> QueryResult<Vec<Self>> {
+        user_subreddit_role
+            .filter(user_id.eq(for_user_id))
+            .order(id)
+            .load(conn)
+    }
+
+    /// Find a given `UserSubredditRole` by its ID.
+    ///
+    /// Internally uses a cache to limit database accesses.
+    #[inline]
+    pub fn find_id(conn: &Database, role_id: i32) -> QueryResult<Self> {
+        let mut cache = CACHE.lock();
+        if cache.contains_key(&role_id) {
+            Ok(cache.get_mut(&role_id).unwrap().clone())
+        } else {
+            let result: Self = user_subreddit_role.find(role_id).first(conn)?;
+            cache.insert(role_id, result.clone());
+            Ok(result)
+        }
+    }
+
+    /// Does the `User` hold the role in the given subreddit?
+    ///
+    /// Always queries the database,
+    /// as permissions must never be out of date.
+    #[inline]
+    pub fn has_role(
+        conn: &Database,
+        for_user_id: i32,
+        subreddit_name: &str,
+        expected_role: Role,
+    ) -> QueryResult<bool> {
+        diesel::select(exists(
+            user_subreddit_role
+                .filter(user_id.eq(for_user_id))
+                .filter(subreddit.eq(subreddit_name.to_lowercase()))
+                .filter(role.eq(expected_role.to_string())),
+        ))
+        .get_result(conn)
+    }
+
+    /// Create a `UserSubredditRole` given the data.
+    ///
+    /// The inserted row is added to the global cache and returned.
+    /// The creation is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn create(
+        conn: &Database,
+        data: &ExternalInsertUserSubredditRole,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Self> {
+        Self::insert(conn, data, false, actor_id)
+    }
+
+    /// Create a `UserSubredditRole` given the data,
+    /// indicating whether it reflects the state on Reddit.
+    #[inline]
+    fn insert(
+        conn: &Database,
+        data: &ExternalInsertUserSubredditRole,
+        from_reddit: bool,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Self> {
+        let result: Self = diesel::insert_into(user_subreddit_role)
+            .values(&InsertUserSubredditRole {
+                user_id:        data.user_id,
+                subreddit:      data.subreddit.to_lowercase(),
+                role:           data.role.to_string(),
+                is_from_reddit: from_reddit,
+            })
+            .get_result(conn)?;
+        CACHE.lock().insert(result.id, result.clone());
+        AuditLog::record(conn, actor_id, AuditAction::Create, None, Some(&result))?;
+
+        let _ = Message {
+            room:      Room::User,
+            action:    Action::Create,
+            data_type: DataType::UserSubredditRole,
+            data:      &result,
+        }
+        .send();
+
+        Ok(result)
+    }
+
+    /// Update a `UserSubredditRole` given an ID and the data to update.
+    ///
+    /// The entry is updated in the database, added to cache, and returned.
+    /// The change is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn update(
+        conn: &Database,
+        role_id: i32,
+        data: &ExternalUpdateUserSubredditRole,
+        actor_id: Option<i32>,
+    ) -> QueryResult<Self> {
+        let old = Self::find_id(conn, role_id)?;
+        let data = UpdateUserSubredditRole {
+            role: data.role.map(|new_role| new_role.to_string()),
+        };
+
+        let result: Self = diesel::update(user_subreddit_role)
+            .filter(id.eq(role_id))
+            .set(&data)
+            .get_result(conn)?;
+        CACHE.lock().insert(result.id, result.clone());
+        AuditLog::record(
+            conn,
+            actor_id,
+            AuditAction::Update,
+            Some(&old),
+            Some(&result),
+        )?;
+
+        let _ = Message {
+            room:      Room::User,
+            action:    Action::Update,
+            data_type: DataType::UserSubredditRole,
+            data:      &Update::new(role_id, &data),
+        }
+        .send();
+
+        Ok(result)
+    }
+
+    /// Delete a `UserSubredditRole` given its ID.
+    ///
+    /// Removes the entry from cache and returns the number of rows deleted (should be `1`).
+    /// The deletion is recorded in the audit log as performed by `actor_id`.
+    #[inline]
+    pub fn delete(conn: &Database, role_id: i32, actor_id: Option<i32>) -> QueryResult<usize> {
+        let old = Self::find_id(conn, role_id)?;
+        AuditLog::record(conn, actor_id, AuditAction::Delete, Some(&old), None)?;
+        CACHE.lock().remove(&role_id);
+
+        let _ = Message {
+            room:      Room::User,
+            action:    Action::Delete,
+            data_type: DataType::UserSubredditRole,
+            data:      &json!({ "id": role_id }),
+        }
+        .send();
+
+        diesel::delete(user_subreddit_role)
+            .filter(id.eq(role_id))
+            .execute(conn)
+    }
+
+    /// Set the subreddits in which the `User` holds a role on Reddit,
+    /// creating and deleting entries as necessary.
+    ///
+    /// Only roles previously synced from Reddit are deleted;
+    /// any granted by a global admin are left in place.
+    ///
+    /// Returns all roles held by the user afterwards.
+    /// As this reflects the state on Reddit, changes are not attributed to any user.
+    #[inline]
+    pub fn set_subreddits(
+        conn: &Database,
+        for_user_id: i32,
+        for_role: Role,
+        subreddits: &[String],
+    ) -> QueryResult<Vec<Self>> {
+        let subreddits: Vec<_> = subreddits.iter().map(|name| name.to_lowercase()).collect();
+        let current: Vec<_> = Self::find_by_user(conn, for_user_id)?
+            .into_iter()
+            .filter(|value| value.role == for_role.to_string())
+            .collect();
+
+        for value in &current {
+            if value.is_from_reddit && !subreddits.contains(&value.subreddit) {
+                Self::delete(conn, value.id, None)?;
+            }
+        }
+
+        for name in subreddits {
+            if !current.iter().any(|value| value.subreddit == name) {
+                Self::insert(
+                    conn,
+                    &ExternalInsertUserSubredditRole {
+                        user_id:   for_user_id,
+                        subreddit: name,
+                        role:      for_role,
+                    },
+                    true,
+                    None,
+                )?;
+            }
+        }
+
+        Self::find_by_user(conn, for_user_id)
+    }
+}
+
+impl Audited for UserSubredditRole {
+    #[inline]
+    fn audit_entity(&self) -> AuditEntity {
+        AuditEntity {
+            data_type: DataType::UserSubredditRole,
+            id:        self.id,
+            thread_id: None,
+        }
+    }
+}
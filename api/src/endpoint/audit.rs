@@ -0,0 +1,55 @@
+use crate::{
+    controller::{AuditLog, AuditLogFilter, User},
+    endpoint::helpers::RocketResult,
+    DataDB,
+};
+use rocket::{get, http::Status};
+use rocket_contrib::json::Json;
+
+/// The number of entries returned when no limit is provided.
+const DEFAULT_LIMIT: i64 = 100;
+
+/// The largest number of entries that may be requested at once.
+const MAX_LIMIT: i64 = 1000;
+
+/// Get the most recent entries in the audit log, newest first.
+///
+/// Entries for a given thread may be viewed by anyone able to modify the thread.
+/// Otherwise, the audit log is restricted to global admins.
+///
+/// A limit outside of `1..=MAX_LIMIT` is rejected.
+#[inline]
+#[get("/?<thread_id>&<user_id>&<since>&<until>&<limit>")]
+pub fn all(
+    conn: DataDB,
+    user: User,
+    thread_id: Option<i32>,
+    user_id: Option<i32>,
+    since: Option<i64>,
+    until: Option<i64>,
+    limit: Option<i64>,
+) -> RocketResult<Json<Vec<AuditLog>>> {
+    let authorized = match thread_id {
+        Some(thread_id) => user.can_modify_thread(&conn, thread_id),
+        None => user.is_global_admin,
+    };
+    if !authorized {
+        return Err(Status::Unauthorized);
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(Status::BadRequest);
+    }
+
+    json_result!(AuditLog::find_filtered(
+        &conn,
+        &AuditLogFilter {
+            thread_id,
+            actor_user_id: user_id,
+            since,
+            until,
+            limit,
+        },
+    ))
+}
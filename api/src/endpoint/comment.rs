@@ -0,0 +1,120 @@
+use crate::{
+    controller::{Comment, ExternalInsertComment, Section, Thread, UpdateComment, User},
+    endpoint::helpers::{boxed_error_mapper, RocketResult},
+    reddit_queue,
+    DataDB,
+};
+use rocket::{delete, http::Status, patch, post, response::status::Created};
+use rocket_contrib::json::Json;
+
+generic_all!(Comment);
+generic_get!(Comment);
+
+/// Ensure each of the provided `Section`s belongs to the `Thread`.
+#[inline]
+fn sections_in_thread(conn: &DataDB, thread_id: i32, sections_id: &[i32]) -> bool {
+    sections_id.iter().all(|&section_id| {
+        Section::find_id(conn, section_id)
+            .map(|section| section.in_thread_id == thread_id)
+            .unwrap_or(false)
+    })
+}
+
+/// Create a `Comment`.
+///
+/// The comment is posted to Reddit in the background,
+/// along with any other pending changes to the thread.
+#[inline]
+#[post("/", data = "<data>")]
+pub fn post(
+    conn: DataDB,
+    user: User,
+    data: Json<ExternalInsertComment>,
+) -> RocketResult<Created<Json<Comment>>> {
+    if !user.can_modify_thread(&conn, data.in_thread_id) {
+        return Err(Status::Unauthorized);
+    }
+
+    let thread = Thread::find_id(&conn, data.in_thread_id).map_err(|_| Status::NotFound)?;
+
+    // Only moderators are able to sticky comments.
+    if data.is_stickied == Some(true)
+        && !user.is_moderator_of(&conn, thread.subreddit.as_ref().map(String::as_str))
+    {
+        return Err(Status::Unauthorized);
+    }
+
+    let sections_id = data.sections_id.as_ref().map_or(&[][..], Vec::as_slice);
+    if !sections_in_thread(&conn, thread.id, sections_id) {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let ret_val = created!(Comment::create(&conn, &data, Some(user.id)));
+    reddit_queue::enqueue(thread.id);
+    ret_val
+}
+
+/// Update a `Comment`.
+///
+/// Changes to the content are pushed to Reddit in the background,
+/// while changes to the stickied state are applied immediately.
+#[inline]
+#[patch("/<id>", data = "<data>")]
+pub fn patch(
+    conn: DataDB,
+    user: User,
+    id: i32,
+    data: Json<UpdateComment>,
+) -> RocketResult<Json<Comment>> {
+    let comment = Comment::find_id(&conn, id).map_err(|_| Status::NotFound)?;
+
+    if !user.can_modify_thread(&conn, comment.in_thread_id) {
+        return Err(Status::Unauthorized);
+    }
+
+    // The contents of the continuation comment are managed automatically.
+    if comment.is_continuation && data.sections_id.is_some() {
+        return Err(Status::PreconditionFailed);
+    }
+
+    if let Some(sections_id) = &data.sections_id {
+        if !sections_in_thread(&conn, comment.in_thread_id, sections_id) {
+            return Err(Status::UnprocessableEntity);
+        }
+    }
+
+    if let Some(is_stickied) = data.is_stickied {
+        if is_stickied != comment.is_stickied {
+            let thread =
+                Thread::find_id(&conn, comment.in_thread_id).map_err(|_| Status::NotFound)?;
+            if !user.is_moderator_of(&conn, thread.subreddit.as_ref().map(String::as_str)) {
+                return Err(Status::Unauthorized);
+            }
+
+            comment
+                .set_sticky_on_reddit(&conn, is_stickied)
+                .map_err(boxed_error_mapper)?;
+        }
+    }
+
+    let ret_val = json_result!(Comment::update(&conn, id, &data, Some(user.id)));
+    reddit_queue::enqueue(comment.in_thread_id);
+    ret_val
+}
+
+/// Delete a `Comment`, both on Reddit and in the database.
+#[inline]
+#[delete("/<id>")]
+pub fn delete(conn: DataDB, user: User, id: i32) -> RocketResult<Status> {
+    let comment = Comment::find_id(&conn, id).map_err(|_| Status::NotFound)?;
+
+    if !user.can_modify_thread(&conn, comment.in_thread_id) {
+        return Err(Status::Unauthorized);
+    }
+
+    comment
+        .delete_on_reddit(&conn)
+        .map_err(boxed_error_mapper)?;
+
+    no_content!(Comment::delete(&conn, id, Some(user.id)))
+}
@@ -1,13 +1,24 @@
 use crate::{
-    controller::{Event, InsertEvent, Thread, UpdateEvent, User},
-    endpoint::helpers::RocketResult,
+    controller::{
+        Event,
+        EventRevision,
+        ExternalLockEvent,
+        InsertEvent,
+        LockEvent,
+        Thread,
+        UpdateEvent,
+        User,
+    },
+    endpoint::helpers::{IfMatch, RocketResult, Versioned},
+    reddit_queue,
     DataDB,
 };
-use rocket::{delete, http::Status, patch, post, response::status::Created};
+use rocket::{delete, get, http::Status, patch, post, response::status::Created};
 use rocket_contrib::json::Json;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-generic_all!(Event);
-generic_get!(Event);
+generic_all!(Event, include_deleted);
+generic_get!(Event, include_deleted, versioned);
 
 /// Create an `Event`.
 #[inline]
@@ -16,7 +27,7 @@ pub fn post(
     conn: DataDB,
     user: User,
     data: Json<InsertEvent>,
-) -> RocketResult<Created<Json<Event>>> {
+) -> RocketResult<Versioned<Created<Json<Event>>>> {
     if !user.can_modify_thread(&conn, data.in_thread_id) {
         return Err(Status::Unauthorized);
     }
@@ -41,10 +52,12 @@ pub fn post(
         return Err(Status::UnprocessableEntity);
     }
 
-    let ret_val = created!(Event::create(&conn, &data));
-    thread
-        .update_on_reddit(&conn)
-        .expect("error updating on Reddit");
+    let ret_val = created!(
+        Event::create(&conn, &data, Some(user.id)),
+        include_deleted,
+        versioned
+    );
+    reddit_queue::enqueue(thread.id);
     ret_val
 }
 
@@ -55,26 +68,33 @@ pub fn post(
 /// we're expecting a regular `UpdateEvent` object.
 /// When updating a single column,
 /// we're expecting an array containing the [key, new value].
+/// When updating the lock,
+/// we're expecting an `ExternalLockEvent` object.
 #[derive(serde::Deserialize)]
 #[serde(untagged)]
 pub enum UpdateEventDiscriminant {
     FullEvent(UpdateEvent),
     PartialEvent(Vec<(usize, serde_json::Value)>),
+    LockEvent(ExternalLockEvent),
 }
 
-/// Discriminate between the two types,
-/// calling the `patch_full_event` method as necessary.
+/// Discriminate between the three types,
+/// calling the `patch_full_event` or `set_lock` method as necessary.
+///
+/// If an `If-Match` header is provided,
+/// the event is only updated if its version matches.
 #[inline]
 #[patch("/<id>", data = "<data>")]
 pub fn patch(
     conn: DataDB,
     user: User,
+    if_match: IfMatch,
     id: i32,
     data: Json<UpdateEventDiscriminant>,
-) -> RocketResult<Json<Event>> {
+) -> RocketResult<Versioned<Json<Event>>> {
     use UpdateEventDiscriminant::*;
     match data.into_inner() {
-        FullEvent(data) => patch_full_event(conn, user, id, data),
+        FullEvent(data) => patch_full_event(conn, user, if_match, id, data),
         PartialEvent(data) => {
             let mut event = match Event::find_id(&conn, id) {
                 Ok(event) => event,
@@ -90,6 +110,7 @@ pub fn patch(
             patch_full_event(
                 conn,
                 user,
+                if_match,
                 id,
                 UpdateEvent {
                     cols: Some(event.cols),
@@ -97,17 +118,57 @@ pub fn patch(
                 },
             )
         }
+        LockEvent(data) => set_lock(conn, user, id, data),
     }
 }
 
+/// Set the lock on an `Event`,
+/// preventing any other `User`s from updating or deleting it.
+///
+/// Returns `403 Forbidden` if the user isn't setting the lock to themselves,
+/// or they possess the lock and are trying to set it to another user.
+#[inline]
+fn set_lock(
+    conn: DataDB,
+    user: User,
+    id: i32,
+    data: ExternalLockEvent,
+) -> RocketResult<Versioned<Json<Event>>> {
+    let event = match Event::find_id(&conn, id) {
+        Ok(event) => event,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    // Ensure the user possesses the authority to modify the lock if able to.
+    if !user.can_modify_thread(&conn, event.in_thread_id) {
+        return Err(Status::Unauthorized);
+    }
+
+    let current_unix_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    if_permitted!(Event::set_lock(
+        &conn,
+        id,
+        &LockEvent::new(data.lock_held_by_user_id, current_unix_timestamp),
+        &user,
+    ))
+    .map(|value| Versioned(value.version, Json(value)))
+}
+
 /// Update the `Event` on Reddit and in the database.
+///
+/// Returns `409 Conflict` if another user holds an unexpired lock on the event.
 #[inline]
 pub fn patch_full_event(
     conn: DataDB,
     user: User,
+    if_match: IfMatch,
     id: i32,
     data: UpdateEvent,
-) -> RocketResult<Json<Event>> {
+) -> RocketResult<Versioned<Json<Event>>> {
     let event = match Event::find_id(&conn, id) {
         Ok(event) => event,
         Err(_) => return Err(Status::NotFound),
@@ -117,20 +178,67 @@ pub fn patch_full_event(
         return Err(Status::Unauthorized);
     }
 
-    let ret_val = json_result!(Event::update(&conn, id, &data));
+    let ret_val = if_permitted!(Event::update(&conn, id, if_match.0, &data, Some(user.id)))
+        .map(|value| Versioned(value.version, Json(value)));
+    reddit_queue::enqueue(event.in_thread_id);
+    ret_val
+}
+
+/// Get all revisions of a `Event`, newest first.
+#[inline]
+#[get("/<id>/revisions")]
+pub fn revisions(conn: DataDB, id: i32) -> RocketResult<Json<Vec<EventRevision>>> {
+    if Event::find_id(&conn, id).is_err() {
+        return Err(Status::NotFound);
+    }
+
+    json_result!(EventRevision::find_by_event(&conn, id))
+}
 
-    Thread::find_id(&conn, event.in_thread_id)
-        .expect("thread not found")
-        .update_on_reddit(&conn)
-        .expect("error updating on Reddit");
+/// Restore a `Event` to a previous revision.
+///
+/// As with any other update,
+/// the change is sent over WebSockets and pushed to Reddit in the background,
+/// and an `If-Match` header is honoured.
+/// Likewise, the restore is rejected if another user holds the event's lock.
+#[inline]
+#[patch("/<id>/revisions/<revision_id>/restore")]
+pub fn restore(
+    conn: DataDB,
+    user: User,
+    if_match: IfMatch,
+    id: i32,
+    revision_id: i32,
+) -> RocketResult<Versioned<Json<Event>>> {
+    let event = match Event::find_id(&conn, id) {
+        Ok(event) => event,
+        Err(_) => return Err(Status::NotFound),
+    };
 
+    if !user.can_modify_thread(&conn, event.in_thread_id) {
+        return Err(Status::Unauthorized);
+    }
+
+    let ret_val = if_permitted!(Event::restore(
+        &conn,
+        id,
+        revision_id,
+        if_match.0,
+        Some(user.id)
+    ))
+    .map(|value| Versioned(value.version, Json(value)));
+    reddit_queue::enqueue(event.in_thread_id);
     ret_val
 }
 
 /// Delete an `Event` as well as any references to its ID.
+///
+/// If an `If-Match` header is provided,
+/// the event is only deleted if its version matches.
+/// Returns `409 Conflict` if another user holds an unexpired lock on the event.
 #[inline]
 #[delete("/<id>")]
-pub fn delete(conn: DataDB, user: User, id: i32) -> RocketResult<Status> {
+pub fn delete(conn: DataDB, user: User, if_match: IfMatch, id: i32) -> RocketResult<Status> {
     let event = match Event::find_id(&conn, id) {
         Ok(event) => event,
         Err(_) => return Err(Status::Unauthorized),
@@ -140,12 +248,27 @@ pub fn delete(conn: DataDB, user: User, id: i32) -> RocketResult<Status> {
         return Err(Status::Unauthorized);
     }
 
-    let ret_val = no_content!(Event::delete(&conn, id));
+    let ret_val = if_permitted!(Event::delete(&conn, id, if_match.0, Some(user.id)))
+        .map(|_| Status::NoContent);
+    reddit_queue::enqueue(event.in_thread_id);
+    ret_val
+}
+
+/// Undelete a `Event`,
+/// re-inserting it into its thread at the position it was deleted from.
+#[inline]
+#[patch("/<id>/undelete")]
+pub fn undelete(conn: DataDB, user: User, id: i32) -> RocketResult<Versioned<Json<Event>>> {
+    let event = match Event::find_id_including_deleted(&conn, id) {
+        Ok(event) => event,
+        Err(_) => return Err(Status::NotFound),
+    };
 
-    Thread::find_id(&conn, event.in_thread_id)
-        .expect("thread not found")
-        .update_on_reddit(&conn)
-        .expect("error updating on Reddit");
+    if !user.can_modify_thread(&conn, event.in_thread_id) {
+        return Err(Status::Unauthorized);
+    }
 
+    let ret_val = versioned_result!(Event::undelete(&conn, id, Some(user.id)));
+    reddit_queue::enqueue(event.in_thread_id);
     ret_val
 }
@@ -1,6 +1,7 @@
 /// Generate an endpoint for fetching all of the provided type.
 ///
 /// This macro should suffice for all types.
+/// Pass `include_deleted` for types that are soft deleted.
 #[macro_export]
 macro_rules! generic_all {
     ($x:ident) => {
@@ -12,11 +13,35 @@ macro_rules! generic_all {
             json_result!($x::find_all(&conn))
         }
     };
+
+    // Types that may be deleted and undeleted can include deleted values,
+    // though only global admins are permitted to do so.
+    ($x:ident, include_deleted) => {
+        #[inline]
+        #[rocket::get("/?<include_deleted>")]
+        pub fn all(
+            conn: crate::DataDB,
+            user: Option<crate::controller::User>,
+            include_deleted: Option<bool>,
+        ) -> crate::endpoint::helpers::RocketResult<rocket_contrib::json::Json<Vec<$x>>> {
+            if include_deleted != Some(true) {
+                return json_result!($x::find_all(&conn));
+            }
+
+            match user {
+                Some(ref user) if user.is_global_admin => {
+                    json_result!($x::find_all_including_deleted(&conn))
+                }
+                _ => Err(rocket::http::Status::Unauthorized),
+            }
+        }
+    };
 }
 
 /// Generate an endpoint for fetching a specific instance of the provided type.
 ///
 /// This macro should suffice for all types.
+/// Pass `include_deleted, versioned` for types that are soft deleted and carry a version.
 #[macro_export]
 macro_rules! generic_get {
     ($x:ident) => {
@@ -29,4 +54,31 @@ macro_rules! generic_get {
             json_result!($x::find_id(&conn, id))
         }
     };
+
+    // Types that may be deleted and undeleted can include deleted values,
+    // though only global admins are permitted to do so.
+    // The `ETag` header is set to the version of the value.
+    ($x:ident, include_deleted, versioned) => {
+        #[inline]
+        #[rocket::get("/<id>?<include_deleted>")]
+        pub fn get(
+            conn: crate::DataDB,
+            user: Option<crate::controller::User>,
+            id: i32,
+            include_deleted: Option<bool>,
+        ) -> crate::endpoint::helpers::RocketResult<
+            crate::endpoint::helpers::Versioned<rocket_contrib::json::Json<$x>>,
+        > {
+            if include_deleted != Some(true) {
+                return versioned_result!($x::find_id(&conn, id));
+            }
+
+            match user {
+                Some(ref user) if user.is_global_admin => {
+                    versioned_result!($x::find_id_including_deleted(&conn, id))
+                }
+                _ => Err(rocket::http::Status::Unauthorized),
+            }
+        }
+    };
 }
@@ -1,16 +1,125 @@
-use rocket::http::Status;
-use rocket_contrib::databases::diesel::result::Error;
+use crate::controller::Rejection;
+use rocket::{
+    http::Status,
+    request::{self, FromRequest, Request},
+    response::{self, Responder, Response},
+    Outcome,
+};
+use rocket_contrib::databases::diesel::result::{DatabaseErrorKind, Error};
 
 pub type RocketResult<T> = Result<T, Status>;
 
+/// The version a client expects an entity to be at,
+/// taken from the `If-Match` header.
+///
+/// Contains `None` if the header is absent or is `*`,
+/// in which case the entity may be modified regardless of its version.
+#[derive(Debug, Clone, Copy)]
+pub struct IfMatch(pub Option<i32>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for IfMatch {
+    type Error = &'a str;
+
+    /// Parse the `If-Match` header, which must contain a single ETag if present.
+    #[inline]
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let header = match request.headers().get_one("If-Match") {
+            Some(header) => header.trim(),
+            None => return Outcome::Success(IfMatch(None)),
+        };
+
+        if header == "*" {
+            return Outcome::Success(IfMatch(None));
+        }
+
+        match header.trim_start_matches("W/").trim_matches('"').parse() {
+            Ok(version) => Outcome::Success(IfMatch(Some(version))),
+            Err(_) => Outcome::Failure((
+                Status::BadRequest,
+                r#"Expected "If-Match" header to contain a single ETag"#,
+            )),
+        }
+    }
+}
+
+/// The sequence number of the last message received by a reconnecting event stream,
+/// taken from the `Last-Event-ID` header.
+#[derive(Debug, Clone, Copy)]
+pub struct LastEventId(pub Option<u64>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for LastEventId {
+    type Error = &'a str;
+
+    #[inline]
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let header = match request.headers().get_one("Last-Event-ID") {
+            Some(header) => header.trim(),
+            None => return Outcome::Success(LastEventId(None)),
+        };
+
+        match header.parse() {
+            Ok(seq) => Outcome::Success(LastEventId(Some(seq))),
+            Err(_) => Outcome::Failure((
+                Status::BadRequest,
+                r#"Expected "Last-Event-ID" header to contain a sequence number"#,
+            )),
+        }
+    }
+}
+
+/// A response for a versioned entity,
+/// setting the `ETag` header to its version.
+#[derive(Debug)]
+pub struct Versioned<R>(pub i32, pub R);
+
+impl<'r, R: Responder<'r>> Responder<'r> for Versioned<R> {
+    #[inline]
+    fn respond_to(self, request: &Request<'_>) -> response::Result<'r> {
+        Response::build_from(self.1.respond_to(request)?)
+            .raw_header("ETag", format!(r#""{}""#, self.0))
+            .ok()
+    }
+}
+
 #[inline]
 pub fn error_mapper(err: Error) -> Status {
     match err {
         Error::NotFound => Status::NotFound,
+        Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => Status::Conflict,
         _ => Status::InternalServerError,
     }
 }
 
+#[inline]
+pub fn reddit_error_mapper(err: reddit::Error) -> Status {
+    match err {
+        reddit::Error::Api(_) => Status::UnprocessableEntity,
+        reddit::Error::InsufficientScope => Status::Unauthorized,
+        reddit::Error::RateLimited(_) => Status::TooManyRequests,
+        reddit::Error::Status(status) if status.as_u16() == 429 => Status::TooManyRequests,
+        _ => Status::BadGateway,
+    }
+}
+
+#[inline]
+pub fn rejection_mapper(rejection: Rejection) -> Status {
+    match rejection {
+        Rejection::VersionMismatch | Rejection::OrderingMismatch => Status::PreconditionFailed,
+        Rejection::Locked => Status::Conflict,
+        Rejection::Forbidden => Status::Forbidden,
+    }
+}
+
+/// Map an error that may have originated from Reddit,
+/// treating anything else as an internal error.
+#[inline]
+pub fn boxed_error_mapper(err: Box<dyn std::error::Error>) -> Status {
+    match err.downcast::<reddit::Error>() {
+        Ok(err) => reddit_error_mapper(*err),
+        Err(_) => Status::InternalServerError,
+    }
+}
+
 #[macro_export]
 macro_rules! json_result {
     ($x:expr) => {
@@ -19,6 +128,35 @@ macro_rules! json_result {
     };
 }
 
+#[macro_export]
+macro_rules! versioned_result {
+    ($x:expr) => {
+        $x.map(|value| {
+            crate::endpoint::helpers::Versioned(value.version, rocket_contrib::json::Json(value))
+        })
+        .map_err(crate::endpoint::helpers::error_mapper)
+    };
+}
+
+/// For operations conditional on the version of an entity,
+/// where `None` indicates the entity has since been modified.
+#[macro_export]
+macro_rules! if_version {
+    ($x:expr) => {
+        $x.map_err(crate::endpoint::helpers::error_mapper)
+            .and_then(|value| value.ok_or(rocket::http::Status::PreconditionFailed))
+    };
+}
+
+/// For operations conditional on both the version and the lock of an entity.
+#[macro_export]
+macro_rules! if_permitted {
+    ($x:expr) => {
+        $x.map_err(crate::endpoint::helpers::error_mapper)
+            .and_then(|value| value.map_err(crate::endpoint::helpers::rejection_mapper))
+    };
+}
+
 #[macro_export]
 macro_rules! no_content {
     ($x:expr) => {
@@ -37,4 +175,17 @@ macro_rules! created {
             ))
             .map_err(crate::endpoint::helpers::error_mapper)
     };
+
+    // For versioned types, setting the `ETag` header.
+    ($x:expr, include_deleted, versioned) => {
+        $x
+            .map(|value| crate::endpoint::helpers::Versioned(
+                value.version,
+                rocket::response::status::Created(
+                    rocket::uri!(get: value.id, _).to_string(),
+                    Some(rocket_contrib::json::Json(value))
+                )
+            ))
+            .map_err(crate::endpoint::helpers::error_mapper)
+    };
 }
@@ -3,9 +3,13 @@ mod helpers;
 #[macro_use]
 mod generics;
 
+pub mod audit;
+pub mod comment;
 pub mod event;
 pub mod meta;
 pub mod oauth;
 pub mod section;
+pub mod stream;
 pub mod thread;
 pub mod user;
+pub mod user_subreddit_role;
@@ -1,11 +1,13 @@
 #[cfg(test)]
 use crate::guid;
 use crate::{
-    controller::{Claim, InsertUser, User},
+    controller::{Claim, InsertUser, UpdateUser, User},
     encryption::encrypt,
     DataDB,
 };
 use lazy_static::lazy_static;
+#[cfg(not(test))]
+use log::error;
 use reddit::Reddit;
 use request::Url;
 use reqwest as request;
@@ -16,26 +18,38 @@ use rocket::{
     http::{Cookie, Cookies, RawStr},
     response::Redirect,
 };
+use rocket_contrib::databases::diesel::result::Error as DieselError;
 #[cfg(test)]
 use std::time::{Duration, SystemTime};
-use std::{error::Error, time::UNIX_EPOCH};
+use std::{env, error::Error, time::UNIX_EPOCH};
 
 lazy_static! {
+    // Unlike the credentials, the base URLs are read at runtime,
+    // allowing staging environments and tests to point at a local stand-in for Reddit.
+    static ref REDDIT_AUTH_BASE_URL: String = env::var("REDDIT_AUTH_BASE_URL")
+        .unwrap_or_else(|_| reddit::DEFAULT_AUTH_BASE_URL.into());
+    static ref REDDIT_API_BASE_URL: String = env::var("REDDIT_API_BASE_URL")
+        .unwrap_or_else(|_| reddit::DEFAULT_API_BASE_URL.into());
+
     pub static ref REDDIT: Reddit<'static> = Reddit::builder()
         .redirect_uri(dotenv!("REDDIT_REDIRECT_URI"))
         .user_agent(dotenv!("REDDIT_USER_AGENT"))
         .client_id(dotenv!("REDDIT_CLIENT_ID"))
         .secret(dotenv!("REDDIT_SECRET"))
         .permanent(true)
+        .auth_base_url(REDDIT_AUTH_BASE_URL.as_str())
+        .api_base_url(REDDIT_API_BASE_URL.as_str())
         .scopes({
             use reddit::Scope::*;
             &[
-                Account,  // Find language
-                Identity, // Find username
-                Submit,   // Submit threads
-                Edit,     // Update threads
-                ModPosts, // (Moderators) Approve a post so it's visible
-                ModFlair, // (Moderators) Add/remove/edit a flair on the submission
+                Account,      // Find language
+                Identity,     // Find username
+                Submit,       // Submit threads and comments
+                Edit,         // Update and delete threads and comments
+                ModPosts,     // (Moderators) Approve a post so it's visible, sticky comments
+                Flair,        // Select a flair on the user's own submissions
+                ModFlair,     // (Moderators) Add/remove/edit a flair on the submission
+                MySubreddits, // Find which subreddits the user moderates
             ]
         })
         .build()
@@ -58,25 +72,28 @@ pub fn oauth(
 
     // If the user has previously authenticated with Enceladus on this device,
     // they should have a cookie set with their user ID.
-    // Let's read that and avoid sending the user to re-authenticate.
+    // Let's read that and avoid sending the user to re-authenticate,
+    // unless their token is missing a scope we now request.
     if let Some(user_id) = cookies.get_private("user_id") {
         let user = User::find_id(&conn, user_id.value().parse()?)?;
 
-        // Give the user a token that should be used in the future.
-        let token = Claim::new(user.id).encode()?;
+        if !user.needs_reauth {
+            // Give the user a token that should be used in the future.
+            let token = Claim::new(user.id).encode()?;
 
-        // Attach additional querystring parameters to the provided callback.
-        let callback = Url::parse_with_params(
-            &callback,
-            &[
-                ("user_id", &user.id.to_string()),
-                ("username", &user.reddit_username),
-                ("lang", &user.lang),
-                ("token", &token),
-            ],
-        )?;
-
-        return Ok(Redirect::to(callback.to_string()));
+            // Attach additional querystring parameters to the provided callback.
+            let callback = Url::parse_with_params(
+                &callback,
+                &[
+                    ("user_id", &user.id.to_string()),
+                    ("username", &user.reddit_username),
+                    ("lang", &user.lang),
+                    ("token", &token),
+                ],
+            )?;
+
+            return Ok(Redirect::to(callback.to_string()));
+        }
     }
 
     // We're testing; let's not bother with actual authentication.
@@ -99,19 +116,19 @@ pub fn oauth(
 /// and use that token to obtain the user's username and preferred language.
 /// All of these values are then used to construct a User
 /// which is inserted into the database.
+/// If the user already exists (as they are authenticating again),
+/// their tokens are replaced instead.
 /// We then use the ID returned from the database insertion to generate a
 /// [JSON Web Token](https://jwt.io/), which is the user's bearer token
 /// that should be provided in the header of each request.
 /// Finally, we call the callback URL originally provided,
 /// with the additional queryparams of `user_id`, `username`, `lang`, and `token`.
 ///
-/// Please note that `is_global_admin`, along with any specific subreddit values,
+/// Moderator status is fetched from Reddit once the user is created,
+/// and is kept up to date by `moderator_sync`.
+/// Please note that `is_global_admin`, along with any other subreddit values,
 /// are **not** initialized, but rather use default values.
-/// In the future, there may be an endpoint in the `/v1/user` namespace (or similar)
-/// that will automatically perform verification of moderator status
-/// (or anything else deemed appropriate).
-/// Until that time,
-/// these fields must be managed manually,
+/// These fields must be managed manually,
 /// typically by contacting the database operator.
 #[inline]
 #[cfg_attr(test, allow(unused_variables))]
@@ -145,25 +162,52 @@ pub fn callback(
         lang = reddit_user.lang()?;
     }
 
-    // Insert the user into our database.
-    let user = User::create(
-        &conn,
-        &InsertUser {
-            reddit_username: username.to_owned(),
-            lang: lang.to_owned(),
-            refresh_token: encrypt(reddit_user.refresh_token().as_ref()),
-            is_global_admin: false,
-            spacex__is_host: false,
-            spacex__is_mod: false,
-            spacex__is_slack_member: false,
-            access_token: encrypt(reddit_user.access_token().as_ref()),
-            access_token_expires_at_utc: reddit_user
-                .expires_at()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as i64,
-        },
-    )?;
+    let refresh_token = encrypt(reddit_user.refresh_token().as_ref());
+    let access_token = encrypt(reddit_user.access_token()?);
+    let access_token_expires_at_utc = reddit_user
+        .expires_at()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    // Insert the user into our database,
+    // or update their tokens if they're authenticating again.
+    let user = match User::find_by_reddit_username(&conn, &username) {
+        Ok(user) => User::update(
+            &conn,
+            user.id,
+            &UpdateUser {
+                refresh_token: refresh_token.into(),
+                access_token: access_token.into(),
+                access_token_expires_at_utc: access_token_expires_at_utc.into(),
+                needs_reauth: false.into(),
+                ..UpdateUser::default()
+            },
+        )?,
+        Err(DieselError::NotFound) => User::create(
+            &conn,
+            &InsertUser {
+                reddit_username: username.to_owned(),
+                lang: lang.to_owned(),
+                refresh_token,
+                is_global_admin: false,
+                spacex__is_slack_member: false,
+                access_token,
+                access_token_expires_at_utc,
+                needs_reauth: false,
+            },
+        )?,
+        Err(err) => return Err(err.into()),
+    };
+
+    // Moderators shouldn't have to wait for the next scheduled refresh.
+    // Any failure is retried when the job next runs.
+    #[cfg(not(test))]
+    {
+        if let Err(err) = User::refresh_moderator_status(&conn, user.id) {
+            error!("error refreshing user {} from Reddit: {}", user.id, err);
+        }
+    }
 
     // Add a cookie so we don't have to re-authetnicate when the user visits again.
     cookies.add_private(
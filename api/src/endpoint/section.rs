@@ -4,25 +4,20 @@ use crate::{
         InsertSection,
         LockSection,
         Section,
-        Thread,
+        SectionRevision,
         UpdateSection,
         User,
     },
-    endpoint::helpers::RocketResult,
+    endpoint::helpers::{IfMatch, RocketResult, Versioned},
+    reddit_queue,
     DataDB,
 };
-use rocket::{delete, http::Status, patch, post, response::status::Created};
+use rocket::{delete, get, http::Status, patch, post, response::status::Created};
 use rocket_contrib::json::Json;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-/// How long are section locks able to be held for, guaranteed?
-///
-/// Currently 10 minutes,
-/// although this is an implementation detail and should not be relied upon.
-const LOCK_DURATION_SECONDS: i64 = 10 * 60;
-
-generic_all!(Section);
-generic_get!(Section);
+generic_all!(Section, include_deleted);
+generic_get!(Section, include_deleted, versioned);
 
 /// Create a `Section`.
 #[inline]
@@ -31,18 +26,17 @@ pub fn post(
     conn: DataDB,
     user: User,
     data: Json<InsertSection>,
-) -> RocketResult<Created<Json<Section>>> {
+) -> RocketResult<Versioned<Created<Json<Section>>>> {
     if !user.can_modify_thread(&conn, data.in_thread_id) {
         return Err(Status::Unauthorized);
     }
 
-    let ret_val = created!(Section::create(&conn, &data));
-
-    Thread::find_id(&conn, data.in_thread_id)
-        .expect("thread not found")
-        .update_on_reddit(&conn)
-        .expect("error posting on Reddit");
-
+    let ret_val = created!(
+        Section::create(&conn, &data, Some(user.id)),
+        include_deleted,
+        versioned
+    );
+    reddit_queue::enqueue(data.in_thread_id);
     ret_val
 }
 
@@ -60,30 +54,37 @@ pub enum UpdateSectionDiscriminant {
 
 /// Discriminate between the two types,
 /// calling the appropriate method as necessary.
+///
+/// If an `If-Match` header is provided,
+/// fields aside from the lock are only updated if the section's version matches.
 #[inline]
 #[patch("/<id>", data = "<data>")]
 pub fn patch(
     conn: DataDB,
     user: User,
+    if_match: IfMatch,
     id: i32,
     data: Json<UpdateSectionDiscriminant>,
-) -> RocketResult<Json<Section>> {
+) -> RocketResult<Versioned<Json<Section>>> {
     use UpdateSectionDiscriminant::*;
     match data.into_inner() {
         LockSection(data) => set_lock(conn, user, id, data),
-        UpdateSection(data) => update_fields(conn, user, id, data),
+        UpdateSection(data) => update_fields(conn, user, if_match, id, data),
     }
 }
 
 /// Set the lock on a `Section`,
 /// preventing any other `User`s from updating any fields.
+///
+/// Returns `403 Forbidden` if the user isn't setting the lock to themselves,
+/// or they possess the lock and are trying to set it to another user.
 #[inline]
 fn set_lock(
     conn: DataDB,
     user: User,
     id: i32,
     data: ExternalLockSection,
-) -> RocketResult<Json<Section>> {
+) -> RocketResult<Versioned<Json<Section>>> {
     let section = match Section::find_id(&conn, id) {
         Ok(section) => section,
         Err(_) => return Err(Status::NotFound),
@@ -99,40 +100,26 @@ fn set_lock(
         .unwrap()
         .as_secs() as i64;
 
-    // (1) Let the user assign the (currently null) lock to themselves.
-    // (2) Let the user revoke their own lock.
-    // (3) Let the user renew their own lock.
-    // (4) A user holds a lock, but it has been held beyond the specified minimum duration.
-    //     Allow the requesting user to possess the lock.
-    if (section.lock_held_by_user_id.is_none() && data.lock_held_by_user_id == Some(user.id))
-        || (section.lock_held_by_user_id == Some(user.id) && data.lock_held_by_user_id.is_none())
-        || (section.lock_held_by_user_id == Some(user.id)
-            && data.lock_held_by_user_id == Some(user.id))
-        || (section.lock_assigned_at_utc + LOCK_DURATION_SECONDS <= current_unix_timestamp)
-    {
-        json_result!(Section::set_lock(
-            &conn,
-            id,
-            &LockSection {
-                lock_held_by_user_id: data.lock_held_by_user_id,
-                lock_assigned_at_utc: current_unix_timestamp,
-            }
-        ))
-    } else {
-        // The user isn't setting the lock to themselves,
-        // or they possess the lock and are trying to set it to another user.
-        Err(Status::Forbidden)
-    }
+    if_permitted!(Section::set_lock(
+        &conn,
+        id,
+        &LockSection::new(data.lock_held_by_user_id, current_unix_timestamp),
+        &user,
+    ))
+    .map(|value| Versioned(value.version, Json(value)))
 }
 
 /// Update any fields aside from the lock.
+///
+/// Returns `409 Conflict` if another user holds an unexpired lock on the section.
 #[inline]
 fn update_fields(
     conn: DataDB,
     user: User,
+    if_match: IfMatch,
     id: i32,
     data: UpdateSection,
-) -> RocketResult<Json<Section>> {
+) -> RocketResult<Versioned<Json<Section>>> {
     let section = match Section::find_id(&conn, id) {
         Ok(section) => section,
         Err(_) => return Err(Status::NotFound),
@@ -142,20 +129,67 @@ fn update_fields(
         return Err(Status::Unauthorized);
     }
 
-    let ret_val = json_result!(Section::update(&conn, id, &data));
+    let ret_val = if_permitted!(Section::update(&conn, id, if_match.0, &data, Some(user.id)))
+        .map(|value| Versioned(value.version, Json(value)));
+    reddit_queue::enqueue(section.in_thread_id);
+    ret_val
+}
+
+/// Get all revisions of a `Section`, newest first.
+#[inline]
+#[get("/<id>/revisions")]
+pub fn revisions(conn: DataDB, id: i32) -> RocketResult<Json<Vec<SectionRevision>>> {
+    if Section::find_id(&conn, id).is_err() {
+        return Err(Status::NotFound);
+    }
 
-    Thread::find_id(&conn, section.in_thread_id)
-        .expect("thread not found")
-        .update_on_reddit(&conn)
-        .expect("error updating on Reddit");
+    json_result!(SectionRevision::find_by_section(&conn, id))
+}
 
+/// Restore a `Section` to a previous revision.
+///
+/// As with any other update,
+/// the change is sent over WebSockets and pushed to Reddit in the background,
+/// and an `If-Match` header is honoured.
+/// Likewise, the restore is rejected if another user holds the section's lock.
+#[inline]
+#[patch("/<id>/revisions/<revision_id>/restore")]
+pub fn restore(
+    conn: DataDB,
+    user: User,
+    if_match: IfMatch,
+    id: i32,
+    revision_id: i32,
+) -> RocketResult<Versioned<Json<Section>>> {
+    let section = match Section::find_id(&conn, id) {
+        Ok(section) => section,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    if !user.can_modify_thread(&conn, section.in_thread_id) {
+        return Err(Status::Unauthorized);
+    }
+
+    let ret_val = if_permitted!(Section::restore(
+        &conn,
+        id,
+        revision_id,
+        if_match.0,
+        Some(user.id)
+    ))
+    .map(|value| Versioned(value.version, Json(value)));
+    reddit_queue::enqueue(section.in_thread_id);
     ret_val
 }
 
 /// Delete a `Section` and any references to its ID.
+///
+/// If an `If-Match` header is provided,
+/// the section is only deleted if its version matches.
+/// Returns `409 Conflict` if another user holds an unexpired lock on the section.
 #[inline]
 #[delete("/<id>")]
-pub fn delete(conn: DataDB, user: User, id: i32) -> RocketResult<Status> {
+pub fn delete(conn: DataDB, user: User, if_match: IfMatch, id: i32) -> RocketResult<Status> {
     let section = match Section::find_id(&conn, id) {
         Ok(section) => section,
         Err(_) => return Err(Status::NotFound),
@@ -165,12 +199,27 @@ pub fn delete(conn: DataDB, user: User, id: i32) -> RocketResult<Status> {
         return Err(Status::Unauthorized);
     }
 
-    let ret_val = no_content!(Section::delete(&conn, id));
+    let ret_val = if_permitted!(Section::delete(&conn, id, if_match.0, Some(user.id)))
+        .map(|_| Status::NoContent);
+    reddit_queue::enqueue(section.in_thread_id);
+    ret_val
+}
 
-    Thread::find_id(&conn, section.in_thread_id)
-        .expect("thread not found")
-        .update_on_reddit(&conn)
-        .expect("error updating on Reddit");
+/// Undelete a `Section`,
+/// re-inserting it into its thread at the position it was deleted from.
+#[inline]
+#[patch("/<id>/undelete")]
+pub fn undelete(conn: DataDB, user: User, id: i32) -> RocketResult<Versioned<Json<Section>>> {
+    let section = match Section::find_id_including_deleted(&conn, id) {
+        Ok(section) => section,
+        Err(_) => return Err(Status::NotFound),
+    };
+
+    if !user.can_modify_thread(&conn, section.in_thread_id) {
+        return Err(Status::Unauthorized);
+    }
 
+    let ret_val = versioned_result!(Section::undelete(&conn, id, Some(user.id)));
+    reddit_queue::enqueue(section.in_thread_id);
     ret_val
 }
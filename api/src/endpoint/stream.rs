@@ -0,0 +1,165 @@
+use crate::{
+    controller::User,
+    endpoint::helpers::{LastEventId, RocketResult},
+    websocket::{Event, Room, Subscription},
+};
+use hashbrown::HashSet;
+use rocket::{
+    fairing::{AdHoc, Fairing},
+    get,
+    http::Status,
+    request::Request,
+    response::{self, Responder, Response},
+    State,
+};
+use serde_json::json;
+use std::{
+    env,
+    io::{self, Read},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+/// The maximum size of each chunk written to the client.
+const CHUNK_SIZE: u64 = 4096;
+
+/// How long to wait for a message before sending a comment instead,
+/// keeping the connection alive and detecting clients that have disconnected.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// The streams currently open, along with the maximum number that may be open at once.
+///
+/// Each stream occupies one of Rocket's workers for as long as it is open,
+/// so the limit defaults to half of them, leaving the rest for other requests.
+/// It can be set explicitly with the `STREAM_LIMIT` environment variable.
+#[derive(Debug)]
+pub struct Streams {
+    open:  AtomicUsize,
+    limit: usize,
+}
+
+impl Streams {
+    /// Manage the streams, basing the limit on the configured number of workers.
+    #[inline]
+    pub fn fairing() -> impl Fairing {
+        AdHoc::on_attach("Streams", |rocket| {
+            let limit = env::var("STREAM_LIMIT")
+                .ok()
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(rocket.config().workers as usize / 2);
+
+            Ok(rocket.manage(Streams {
+                open: AtomicUsize::new(0),
+                limit,
+            }))
+        })
+    }
+}
+
+/// Messages sent to the subscribed rooms, formatted as server-sent events.
+pub struct EventStream<'r> {
+    streams:      &'r Streams,
+    subscription: Subscription,
+    /// The event currently being written, and how much of it has been written.
+    pending:      Vec<u8>,
+    written:      usize,
+}
+
+impl EventStream<'_> {
+    #[inline]
+    fn format(event: Event) -> Vec<u8> {
+        match event {
+            Event::Message(seq, message) => format!("id: {}\ndata: {}\n\n", seq, message),
+            Event::Resync(room) => format!(
+                "event: resync\ndata: {}\n\n",
+                json!({ "room": room.to_string() })
+            ),
+        }
+        .into_bytes()
+    }
+}
+
+impl Read for EventStream<'_> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.written == self.pending.len() {
+            let event = match self.subscription.try_recv() {
+                Some(event) => Some(event),
+                // Rocket only flushes what it has read so far once a read would block.
+                // At the start of a chunk, that would instead end the stream,
+                // so wait for the next event in that case.
+                None if buf.len() < CHUNK_SIZE as usize => {
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                None => self.subscription.recv_timeout(KEEP_ALIVE),
+            };
+
+            self.pending = match event {
+                Some(event) => Self::format(event),
+                None => b":\n\n".to_vec(),
+            };
+            self.written = 0;
+        }
+
+        let len = (&self.pending[self.written..]).read(buf)?;
+        self.written += len;
+        Ok(len)
+    }
+}
+
+impl Drop for EventStream<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.streams.open.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<'r> Responder<'r> for EventStream<'r> {
+    #[inline]
+    fn respond_to(self, _: &Request<'_>) -> response::Result<'r> {
+        Response::build()
+            .raw_header("Content-Type", "text/event-stream")
+            .raw_header("Cache-Control", "no-cache")
+            .chunked_body(self, CHUNK_SIZE)
+            .ok()
+    }
+}
+
+/// Stream messages sent to the comma-separated `rooms` as server-sent events,
+/// mirroring what a WebSocket client in those rooms would receive.
+///
+/// Rooms require the same authorization as over WebSockets.
+/// If a `Last-Event-ID` header is provided, as when reconnecting,
+/// any messages missed since then are sent first.
+///
+/// Returns `503 Service Unavailable` if too many streams are already open.
+#[inline]
+#[get("/?<rooms>")]
+pub fn stream<'r>(
+    streams: State<'r, Streams>,
+    user: Option<User>,
+    last_event_id: LastEventId,
+    rooms: String,
+) -> RocketResult<EventStream<'r>> {
+    let rooms: HashSet<Room> = match rooms.split(',').map(str::parse).collect() {
+        Ok(rooms) => rooms,
+        Err(_) => return Err(Status::BadRequest),
+    };
+    if !rooms.iter().all(|room| room.may_join(user.as_ref())) {
+        return Err(Status::Unauthorized);
+    }
+
+    let streams = streams.inner();
+    if streams.open.fetch_add(1, Ordering::Relaxed) >= streams.limit {
+        streams.open.fetch_sub(1, Ordering::Relaxed);
+        return Err(Status::ServiceUnavailable);
+    }
+
+    Ok(EventStream {
+        streams,
+        subscription: Subscription::new(rooms, last_event_id.0),
+        // Sending a comment immediately lets the client know the stream is open.
+        pending: b":\n\n".to_vec(),
+        written: 0,
+    })
+}
@@ -1,97 +1,198 @@
 use crate::{
-    controller::{ExternalInsertThread, Thread, UpdateThread, User},
-    endpoint::helpers::RocketResult,
+    controller::{
+        AuditAction,
+        AuditLog,
+        ExternalInsertThread,
+        RedditSync,
+        Thread,
+        UpdateThread,
+        User,
+    },
+    endpoint::helpers::{
+        boxed_error_mapper,
+        error_mapper,
+        reddit_error_mapper,
+        IfMatch,
+        RocketResult,
+        Versioned,
+    },
+    reddit_queue,
     DataDB,
 };
+use log::error;
 use rocket::{delete, get, http::Status, patch, post, response::status::Created};
 use rocket_contrib::json::{Json, JsonValue};
-use std::collections::BTreeSet;
+use serde_json::json;
 
-generic_all!(Thread);
-generic_get!(Thread);
+generic_all!(Thread, include_deleted);
+generic_get!(Thread, include_deleted, versioned);
 
 /// Get the `Thread` along with its `Section`s, `Event`s, author, and section locks.
 #[inline]
 #[get("/<id>/full")]
 pub fn get_full(conn: DataDB, id: i32) -> RocketResult<JsonValue> {
     Ok(Thread::find_id_with_foreign_keys(&conn, id)
-        .map_err(crate::endpoint::helpers::error_mapper)?
+        .map_err(error_mapper)?
         .into())
 }
 
+/// Get the state of the `Thread`'s post on Reddit,
+/// including when it was last updated, whether it reflects the latest changes,
+/// and what was done to shorten it if it would otherwise be too long.
+#[inline]
+#[get("/<id>/sync")]
+pub fn get_sync(conn: DataDB, id: i32) -> RocketResult<JsonValue> {
+    let thread = Thread::find_id(&conn, id).map_err(error_mapper)?;
+    let sync = RedditSync::find_id(&conn, id).map_err(error_mapper)?;
+    let rendered = thread
+        .render(&conn)
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok(json!({
+        "thread_id": id,
+        "post_id": thread.post_id,
+        "synced_at_utc": sync.as_ref().map(|sync| sync.synced_at_utc),
+        "is_up_to_date": sync.map_or(false, |sync| sync.markdown == rendered.markdown),
+        "overflow": rendered.overflow,
+        "is_pending": reddit_queue::is_pending(id),
+    })
+    .into())
+}
+
+/// Get the link flairs available in the `Thread`'s subreddit.
+#[inline]
+#[get("/<id>/flairs")]
+pub fn flairs(conn: DataDB, user: User, id: i32) -> RocketResult<Json<Vec<reddit::LinkFlair>>> {
+    let thread = Thread::find_id(&conn, id).map_err(error_mapper)?;
+    let subreddit = thread.subreddit.ok_or(Status::PreconditionFailed)?;
+
+    let user_id = user.id;
+    let mut user: reddit::User<'_> = user.into();
+    let flairs = user.link_flairs(&subreddit).map_err(reddit_error_mapper)?;
+    User::update_access_token_if_necessary(&conn, user_id, &mut user)
+        .map_err(boxed_error_mapper)?;
+
+    Ok(Json(flairs))
+}
+
 /// Create a `Thread`.
+///
+/// If a flair is provided, it is set on the post immediately after submission.
+/// As the post already exists at that point,
+/// failing to set the flair doesn't prevent the thread from being created.
 #[inline]
 #[post("/", data = "<data>")]
 pub fn post(
     conn: DataDB,
     user: User,
-    data: Json<ExternalInsertThread>,
-) -> RocketResult<Created<Json<Thread>>> {
+    mut data: Json<ExternalInsertThread>,
+) -> RocketResult<Versioned<Created<Json<Thread>>>> {
     let user_id = user.id;
-    let subreddit = &data.subreddit;
     let mut post_id = None;
+    let mut reddit_user = None;
 
-    if let Some(subreddit) = subreddit {
+    if let Some(subreddit) = data.subreddit.clone() {
         let mut user: reddit::User<'_> = user.into();
-        post_id = Some(
-            user.submit_self_post(subreddit, &data.thread_name, None)
-                .expect("error posting to Reddit"),
+        let reddit_id = user
+            .submit_self_post(&subreddit, &data.thread_name, None)
+            .map_err(reddit_error_mapper)?;
+        let flair = Thread::set_flair_on_reddit(
+            &mut user,
+            &subreddit,
+            &reddit_id,
+            data.flair_id.as_ref().map(String::as_str),
+            data.flair_text.as_ref().map(String::as_str),
         );
+        match flair {
+            Ok(flair_id) => data.flair_id = flair_id,
+            Err(err) => {
+                error!("unable to set flair on post {}: {}", reddit_id, err);
+                data.flair_id = None;
+                data.flair_text = None;
+            }
+        }
+        post_id = Some(reddit_id);
+        reddit_user = Some(user);
+    }
+
+    let thread = created!(
+        Thread::create(&conn, &data, user_id, post_id),
+        include_deleted,
+        versioned
+    );
+
+    if let Some(mut user) = reddit_user {
         User::update_access_token_if_necessary(&conn, user_id, &mut user)
-            .expect("could not update access token");
+            .map_err(boxed_error_mapper)?;
     }
 
-    created!(Thread::create(&conn, &data, user_id, post_id))
+    thread
 }
 
 /// Update a `Thread`.
+///
+/// If an `If-Match` header is provided,
+/// the thread is only updated if its version matches.
+/// Sections and events may only be reordered, not added or removed.
 #[inline]
 #[patch("/<id>", data = "<data>")]
 pub fn patch(
     conn: DataDB,
     user: User,
+    if_match: IfMatch,
     id: i32,
-    data: Json<UpdateThread>,
-) -> RocketResult<Json<Thread>> {
+    mut data: Json<UpdateThread>,
+) -> RocketResult<Versioned<Json<Thread>>> {
     if !user.can_modify_thread(&conn, id) {
         return Err(Status::Unauthorized);
     }
 
-    // Restrict changing `.sections_id` to reordering, not adding or removing.
-    if data.sections_id.is_some() {
-        let current_thread = Thread::find_id(&conn, id).unwrap();
-
-        let current_sections: BTreeSet<_> = current_thread.sections_id.iter().collect();
-        let proposed_sections: BTreeSet<_> = data.sections_id.as_ref().unwrap().iter().collect();
-
-        if current_sections != proposed_sections {
-            return Err(Status::PreconditionFailed);
-        }
+    // Check the version before making any changes on Reddit.
+    // It is checked again when updating, in case of a concurrent change.
+    let current = Thread::find_id(&conn, id).map_err(error_mapper)?;
+    if if_match
+        .0
+        .map_or(false, |version| version != current.version)
+    {
+        return Err(Status::PreconditionFailed);
     }
 
-    // Restrict changing `.events_id` to reordering, not adding or removing.
-    if data.events_id.is_some() {
-        let current_thread = Thread::find_id(&conn, id).unwrap();
-
-        let current_events: BTreeSet<_> = current_thread.events_id.iter().collect();
-        let proposed_events: BTreeSet<_> = data.events_id.as_ref().unwrap().iter().collect();
+    // The flair is set as a whole, so changing either field replaces both.
+    // Changes are applied to Reddit immediately.
+    if data.flair_id.is_some() || data.flair_text.is_some() {
+        let thread = Thread::find_id(&conn, id).map_err(error_mapper)?;
+        let mut flair_id = data.flair_id.clone().and_then(|flair_id| flair_id);
+        let flair_text = data.flair_text.clone().and_then(|flair_text| flair_text);
 
-        if current_events != proposed_events {
-            return Err(Status::PreconditionFailed);
+        if let (Some(subreddit), Some(reddit_id)) = (&thread.subreddit, &thread.post_id) {
+            let user_id = user.id;
+            let mut user: reddit::User<'_> = user.clone().into();
+            flair_id = Thread::set_flair_on_reddit(
+                &mut user,
+                subreddit,
+                reddit_id,
+                flair_id.as_ref().map(String::as_str),
+                flair_text.as_ref().map(String::as_str),
+            )
+            .map_err(reddit_error_mapper)?;
+            User::update_access_token_if_necessary(&conn, user_id, &mut user)
+                .map_err(boxed_error_mapper)?;
         }
-    }
 
-    Thread::find_id(&conn, id)
-        .unwrap()
-        .update_on_reddit(&conn)
-        .unwrap();
+        data.flair_id = Some(flair_id);
+        data.flair_text = Some(flair_text);
+    }
 
-    json_result!(Thread::update(&conn, id, &data))
+    let ret_val = if_permitted!(Thread::update(&conn, id, if_match.0, &data, Some(user.id)))
+        .map(|value| Versioned(value.version, Json(value)));
+    reddit_queue::enqueue(id);
+    ret_val
 }
 
 /// Approve a `Thread` on Reddit.
 /// Does not perform any action in the database,
-/// aside from potentially updating a `User`'s access token.
+/// aside from recording the approval in the audit log
+/// and potentially updating a `User`'s access token.
 #[inline]
 #[patch("/<id>/approve")]
 pub fn approve(conn: DataDB, user: User, id: i32) -> RocketResult<Json<()>> {
@@ -106,15 +207,19 @@ pub fn approve(conn: DataDB, user: User, id: i32) -> RocketResult<Json<()>> {
         Err(_) => return Err(Status::NotFound),
     };
 
-    if !user.is_moderator_of(thread.subreddit.as_ref().map(String::as_str)) {
+    if !user.is_moderator_of(&conn, thread.subreddit.as_ref().map(String::as_str)) {
         return Err(Status::Unauthorized);
     }
 
+    let actor_id = user.id;
     let mut user: reddit::User<'_> = user.into();
-    user.approve(&format!("t3_{}", thread.post_id.unwrap()))
-        .expect("error approving thread");
+    user.approve(&format!("t3_{}", thread.post_id.as_ref().unwrap()))
+        .map_err(reddit_error_mapper)?;
     User::update_access_token_if_necessary(&conn, thread.created_by_user_id, &mut user)
-        .expect("could not update access token");
+        .map_err(boxed_error_mapper)?;
+
+    AuditLog::record_action(&conn, Some(actor_id), AuditAction::Approve, &thread)
+        .map_err(error_mapper)?;
 
     Ok(Json(()))
 }
@@ -139,7 +244,8 @@ pub fn unsticky(conn: DataDB, user: User, id: i32) -> RocketResult<Json<()>> {
 
 /// Sets whether a `Thread` should be stickied or unstickied on Reddit.
 /// Does not perform any action in the database,
-/// aside from potentially updating a `User`'s access token.
+/// aside from recording the change in the audit log
+/// and potentially updating a `User`'s access token.
 #[inline]
 fn set_sticky(conn: DataDB, user: User, id: i32, state: bool) -> RocketResult<Json<()>> {
     let thread = match Thread::find_id(&conn, id) {
@@ -153,26 +259,51 @@ fn set_sticky(conn: DataDB, user: User, id: i32, state: bool) -> RocketResult<Js
         Err(_) => return Err(Status::NotFound),
     };
 
-    if !user.is_moderator_of(thread.subreddit.as_ref().map(String::as_str)) {
+    if !user.is_moderator_of(&conn, thread.subreddit.as_ref().map(String::as_str)) {
         return Err(Status::Unauthorized);
     }
 
+    let actor_id = user.id;
     let mut user: reddit::User<'_> = user.into();
-    user.set_sticky(&format!("t3_{}", thread.post_id.unwrap()), state)
-        .expect("error stickying/unstickying thread");
+    user.set_sticky(&format!("t3_{}", thread.post_id.as_ref().unwrap()), state)
+        .map_err(reddit_error_mapper)?;
     User::update_access_token_if_necessary(&conn, thread.created_by_user_id, &mut user)
-        .expect("could not update access token");
+        .map_err(boxed_error_mapper)?;
+
+    let audit_action = match state {
+        true => AuditAction::Sticky,
+        false => AuditAction::Unsticky,
+    };
+    AuditLog::record_action(&conn, Some(actor_id), audit_action, &thread).map_err(error_mapper)?;
 
     Ok(Json(()))
 }
 
 /// Delete a `Thread`.
+///
+/// If an `If-Match` header is provided,
+/// the thread is only deleted if its version matches.
 #[inline]
 #[delete("/<id>")]
-pub fn delete(conn: DataDB, user: User, id: i32) -> RocketResult<Status> {
+pub fn delete(conn: DataDB, user: User, if_match: IfMatch, id: i32) -> RocketResult<Status> {
     if user.can_modify_thread(&conn, id) {
-        no_content!(Thread::delete(&conn, id))
+        if_version!(Thread::delete(&conn, id, if_match.0, Some(user.id))).map(|_| Status::NoContent)
     } else {
         Err(Status::Unauthorized)
     }
 }
+
+/// Undelete a `Thread`.
+#[inline]
+#[patch("/<id>/undelete")]
+pub fn undelete(conn: DataDB, user: User, id: i32) -> RocketResult<Versioned<Json<Thread>>> {
+    let thread = Thread::find_id_including_deleted(&conn, id).map_err(error_mapper)?;
+
+    if !user.can_modify(&conn, &thread) {
+        return Err(Status::Unauthorized);
+    }
+
+    let ret_val = versioned_result!(Thread::undelete(&conn, id, Some(user.id)));
+    reddit_queue::enqueue(id);
+    ret_val
+}
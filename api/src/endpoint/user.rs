@@ -1,16 +1,18 @@
 #![allow(non_snake_case)]
 
-use crate::controller::User;
+use crate::{
+    controller::{UpdateUserRoles, User, UserSubredditRole},
+    endpoint::helpers::{boxed_error_mapper, error_mapper, RocketResult},
+    DataDB,
+};
+use rocket::{get, http::Status, patch};
+use rocket_contrib::json::{Json, JsonValue};
+use serde_json::json;
 
 #[cfg(debug_assertions)]
 use {
-    crate::{
-        controller::{Claim, ExternalInsertUser, ExternalUpdateUser},
-        endpoint::helpers::RocketResult,
-        DataDB,
-    },
-    rocket::{delete, http::Status, patch, post, response::status::Created},
-    rocket_contrib::json::Json,
+    crate::controller::{Claim, ExternalInsertUser, ExternalUpdateUser},
+    rocket::{delete, post, response::status::Created},
     serde::Serialize,
     std::convert::From,
 };
@@ -18,6 +20,74 @@ use {
 generic_all!(User);
 generic_get!(User);
 
+/// Refresh a `User`'s moderator roles from Reddit,
+/// returning all roles they hold afterwards.
+///
+/// Users may refresh their own roles;
+/// global admins may refresh those of any user.
+#[inline]
+#[patch("/<id>/refresh")]
+pub fn refresh(conn: DataDB, user: User, id: i32) -> RocketResult<Json<Vec<UserSubredditRole>>> {
+    if user.id != id && !user.is_global_admin {
+        return Err(Status::Unauthorized);
+    }
+
+    User::refresh_moderator_status(&conn, id)
+        .map(Json)
+        .map_err(boxed_error_mapper)
+}
+
+/// Represent the roles held by a `User`,
+/// including their roles in specific subreddits.
+#[inline]
+fn roles_json(conn: &DataDB, target: &User) -> RocketResult<JsonValue> {
+    let subreddit_roles = UserSubredditRole::find_by_user(conn, target.id).map_err(error_mapper)?;
+
+    Ok(json!({
+        "user_id": target.id,
+        "is_global_admin": target.is_global_admin,
+        "spacex__is_slack_member": target.spacex__is_slack_member,
+        "subreddit_roles": subreddit_roles,
+    })
+    .into())
+}
+
+/// Get the roles held by a `User`.
+/// Restricted to global admins.
+#[inline]
+#[get("/<id>/roles")]
+pub fn get_roles(conn: DataDB, user: User, id: i32) -> RocketResult<JsonValue> {
+    if !user.is_global_admin {
+        return Err(Status::Unauthorized);
+    }
+
+    let target = User::find_id(&conn, id).map_err(error_mapper)?;
+    roles_json(&conn, &target)
+}
+
+/// Change the role flags of a `User`.
+/// Restricted to global admins.
+///
+/// The last remaining global admin is not able to demote themselves,
+/// as there would then be no way to grant the role to anyone.
+#[inline]
+#[patch("/<id>/roles", data = "<data>")]
+pub fn patch_roles(
+    conn: DataDB,
+    user: User,
+    id: i32,
+    data: Json<UpdateUserRoles>,
+) -> RocketResult<JsonValue> {
+    if !user.is_global_admin {
+        return Err(Status::Unauthorized);
+    }
+
+    match User::update_roles(&conn, id, &data, Some(user.id)).map_err(error_mapper)? {
+        Some(target) => roles_json(&conn, &target),
+        None => Err(Status::PreconditionFailed),
+    }
+}
+
 /// Create a `User`.
 #[cfg(debug_assertions)]
 #[inline]
@@ -55,8 +125,6 @@ pub struct TokenUser {
     reddit_username: String,
     lang: String,
     is_global_admin: bool,
-    spacex__is_host: bool,
-    spacex__is_mod: bool,
     spacex__is_slack_member: bool,
 }
 
@@ -74,8 +142,6 @@ impl From<User> for TokenUser {
             reddit_username: user.reddit_username,
             lang: user.lang,
             is_global_admin: user.is_global_admin,
-            spacex__is_host: user.spacex__is_host,
-            spacex__is_mod: user.spacex__is_mod,
             spacex__is_slack_member: user.spacex__is_slack_member,
         }
     }
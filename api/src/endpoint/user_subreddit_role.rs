@@ -0,0 +1,60 @@
+use crate::{
+    controller::{
+        ExternalInsertUserSubredditRole,
+        ExternalUpdateUserSubredditRole,
+        User,
+        UserSubredditRole,
+    },
+    endpoint::helpers::RocketResult,
+    DataDB,
+};
+use rocket::{delete, http::Status, patch, post, response::status::Created};
+use rocket_contrib::json::Json;
+
+generic_all!(UserSubredditRole);
+generic_get!(UserSubredditRole);
+
+/// Grant a `User` a role in a subreddit.
+/// Restricted to global admins.
+#[inline]
+#[post("/", data = "<data>")]
+pub fn post(
+    conn: DataDB,
+    user: User,
+    data: Json<ExternalInsertUserSubredditRole>,
+) -> RocketResult<Created<Json<UserSubredditRole>>> {
+    if !user.is_global_admin {
+        return Err(Status::Unauthorized);
+    }
+
+    created!(UserSubredditRole::create(&conn, &data, Some(user.id)))
+}
+
+/// Change the role a `User` holds in a subreddit.
+/// Restricted to global admins.
+#[inline]
+#[patch("/<id>", data = "<data>")]
+pub fn patch(
+    conn: DataDB,
+    user: User,
+    id: i32,
+    data: Json<ExternalUpdateUserSubredditRole>,
+) -> RocketResult<Json<UserSubredditRole>> {
+    if !user.is_global_admin {
+        return Err(Status::Unauthorized);
+    }
+
+    json_result!(UserSubredditRole::update(&conn, id, &data, Some(user.id)))
+}
+
+/// Revoke a `User`'s role in a subreddit.
+/// Restricted to global admins.
+#[inline]
+#[delete("/<id>")]
+pub fn delete(conn: DataDB, user: User, id: i32) -> RocketResult<Status> {
+    if !user.is_global_admin {
+        return Err(Status::Unauthorized);
+    }
+
+    no_content!(UserSubredditRole::delete(&conn, id, Some(user.id)))
+}
@@ -41,6 +41,12 @@ impl Fairing for FeatureFilter {
     /// FIXME Is there any valid use case for an "all" feature flag?
     #[inline]
     fn on_response(&self, request: &Request<'_>, response: &mut Response<'_>) {
+        // Streamed bodies, such as server-sent events, may never end,
+        // so they must not be read here.
+        if response.body().map_or(false, |body| body.is_chunked()) {
+            return;
+        }
+
         let features: String = request
             .get_query_value("features")
             .unwrap_or_else(|| Ok("".into()))
@@ -0,0 +1,79 @@
+//! A background job releasing `Section` and `Event` locks that have expired.
+//!
+//! Expired locks may be taken by anyone,
+//! but would otherwise still appear to be held until somebody does so.
+//! Every `LOCK_REAP_INTERVAL` seconds, all expired locks are released,
+//! notifying clients over WebSockets as if the holder had released the lock themselves.
+
+use crate::{
+    controller::{Event, Section},
+    DataPool,
+    Database,
+};
+use lazy_static::lazy_static;
+use log::error;
+use std::{
+    env,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+lazy_static! {
+    /// The time between two checks for expired locks.
+    /// Defaults to thirty seconds.
+    static ref REAP_INTERVAL: Duration = Duration::from_secs(
+        env::var("LOCK_REAP_INTERVAL")
+            .ok()
+            .and_then(|interval| interval.parse().ok())
+            .unwrap_or(30)
+    );
+}
+
+/// Run the job, releasing expired locks indefinitely.
+///
+/// A connection is only taken from the pool while locks are being released.
+#[inline]
+pub fn spawn(pool: DataPool) {
+    loop {
+        match pool.get() {
+            Some(conn) => release_expired(&conn),
+            None => error!("unable to connect to the database to release expired locks"),
+        }
+
+        thread::sleep(*REAP_INTERVAL);
+    }
+}
+
+/// Release all section and event locks that have expired.
+///
+/// Each lock is checked again as it is released,
+/// so a lock renewed or taken in the meantime is left alone.
+#[inline]
+fn release_expired(conn: &Database) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    match Section::find_expired_locks(conn, now) {
+        Ok(sections) => {
+            for value in sections {
+                if let Err(err) = Section::release_expired_lock(conn, value.id, now) {
+                    error!("error releasing lock on section {}: {}", value.id, err);
+                }
+            }
+        }
+        Err(err) => error!("error loading expired section locks: {}", err),
+    }
+
+    match Event::find_expired_locks(conn, now) {
+        Ok(events) => {
+            for value in events {
+                if let Err(err) = Event::release_expired_lock(conn, value.id, now) {
+                    error!("error releasing lock on event {}: {}", value.id, err);
+                }
+            }
+        }
+        Err(err) => error!("error loading expired event locks: {}", err),
+    }
+}
@@ -19,6 +19,9 @@ mod controller;
 mod encryption;
 mod endpoint;
 mod fairing;
+mod lock_reaper;
+mod moderator_sync;
+mod reddit_queue;
 mod rocket_conditional_attach;
 mod schema;
 #[cfg(feature = "telemetry")]
@@ -33,7 +36,11 @@ use endpoint::*;
 use fairing::*;
 use rocket::{routes, Rocket};
 use rocket_conditional_attach::*;
-use rocket_contrib::{database, helmet::SpaceHelmet};
+use rocket_contrib::{
+    database,
+    databases::{r2d2, Poolable},
+    helmet::SpaceHelmet,
+};
 use rocket_cors::Cors;
 #[cfg(feature = "telemetry")]
 use rocket_telemetry::Telemetry;
@@ -43,6 +50,30 @@ pub type Database = diesel::PgConnection;
 #[database("data")]
 pub struct DataDB(Database);
 
+/// A handle to the database pool, for use outside of requests.
+///
+/// The pool is only as large as the number of workers,
+/// so background jobs should only hold a connection while they need one.
+#[derive(Clone)]
+pub struct DataPool(r2d2::Pool<<Database as Poolable>::Manager>);
+
+impl DataPool {
+    /// Get a handle to the pool attached to the `Rocket` instance.
+    #[inline]
+    pub fn new(rocket: &Rocket) -> Option<Self> {
+        rocket
+            .state::<DataDBPool>()
+            .map(|pool| DataPool(pool.0.clone()))
+    }
+
+    /// Take a connection from the pool,
+    /// waiting for one to become available if necessary.
+    #[inline]
+    pub fn get(&self) -> Option<DataDB> {
+        self.0.get().ok().map(DataDB)
+    }
+}
+
 /// Returns a globally unique identifier.
 /// Specifically, v4, which is not based on any input factors.
 #[inline]
@@ -67,6 +98,7 @@ pub fn server() -> Rocket {
         .attach(Cors::default())
         .attach(DataDB::fairing())
         .attach(FeatureFilter::default())
+        .attach(stream::Streams::fairing())
         .attach_if(cfg!(feature = "telemetry"), Telemetry::default())
         .manage(Cors::default())
         .mount("/", rocket_cors::catch_all_options_routes())
@@ -75,9 +107,36 @@ pub fn server() -> Rocket {
         .mount(
             "/v1/user",
             #[cfg(debug_assertions)]
-            routes![user::all, user::get, user::post, user::patch, user::delete],
+            routes![
+                user::all,
+                user::get,
+                user::refresh,
+                user::get_roles,
+                user::patch_roles,
+                user::post,
+                user::patch,
+                user::delete,
+            ],
             #[cfg(not(debug_assertions))]
-            routes![user::all, user::get],
+            routes![
+                user::all,
+                user::get,
+                user::refresh,
+                user::get_roles,
+                user::patch_roles,
+            ],
+        )
+        .mount("/v1/audit", routes![audit::all])
+        .mount("/v1/stream", routes![stream::stream])
+        .mount(
+            "/v1/user_subreddit_role",
+            routes![
+                user_subreddit_role::all,
+                user_subreddit_role::get,
+                user_subreddit_role::post,
+                user_subreddit_role::patch,
+                user_subreddit_role::delete,
+            ],
         )
         .mount(
             "/v1/thread",
@@ -85,12 +144,15 @@ pub fn server() -> Rocket {
                 thread::all,
                 thread::get,
                 thread::get_full,
+                thread::get_sync,
+                thread::flairs,
                 thread::post,
                 thread::patch,
                 thread::approve,
                 thread::sticky,
                 thread::unsticky,
                 thread::delete,
+                thread::undelete,
             ],
         )
         .mount(
@@ -98,9 +160,22 @@ pub fn server() -> Rocket {
             routes![
                 section::all,
                 section::get,
+                section::revisions,
                 section::post,
                 section::patch,
+                section::restore,
                 section::delete,
+                section::undelete,
+            ],
+        )
+        .mount(
+            "/v1/comment",
+            routes![
+                comment::all,
+                comment::get,
+                comment::post,
+                comment::patch,
+                comment::delete,
             ],
         )
         .mount(
@@ -108,9 +183,12 @@ pub fn server() -> Rocket {
             routes![
                 event::all,
                 event::get,
+                event::revisions,
                 event::post,
                 event::patch,
+                event::restore,
                 event::delete,
+                event::undelete,
             ],
         )
 }
@@ -119,20 +197,47 @@ pub fn server() -> Rocket {
 /// Uses the port number defined in the environment variable `ROCKET_PORT`.
 /// If not defined, defaults to `8000`.
 fn main() {
+    #[cfg(feature = "telemetry")]
     std::thread::Builder::new()
-        .name("websocket_server".into())
+        .name("telemetry".into())
         .spawn(|| {
-            websocket::spawn();
+            telemetry::spawn();
         })
         .unwrap();
 
-    #[cfg(feature = "telemetry")]
+    let rocket = server();
+
+    let conn = DataDB::get_one(&rocket).expect("could not connect to database");
     std::thread::Builder::new()
-        .name("telemetry".into())
-        .spawn(|| {
-            telemetry::spawn();
+        .name("websocket_server".into())
+        .spawn(move || {
+            websocket::spawn(conn);
+        })
+        .unwrap();
+
+    let pool = DataPool::new(&rocket).expect("could not connect to database");
+    std::thread::Builder::new()
+        .name("reddit_queue".into())
+        .spawn(move || {
+            reddit_queue::spawn(pool);
+        })
+        .unwrap();
+
+    let pool = DataPool::new(&rocket).expect("could not connect to database");
+    std::thread::Builder::new()
+        .name("moderator_sync".into())
+        .spawn(move || {
+            moderator_sync::spawn(pool);
+        })
+        .unwrap();
+
+    let pool = DataPool::new(&rocket).expect("could not connect to database");
+    std::thread::Builder::new()
+        .name("lock_reaper".into())
+        .spawn(move || {
+            lock_reaper::spawn(pool);
         })
         .unwrap();
 
-    server().launch();
+    rocket.launch();
 }
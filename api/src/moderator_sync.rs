@@ -0,0 +1,62 @@
+//! A background job keeping each `User`'s moderator status in sync with Reddit.
+//!
+//! Moderators are added and removed on Reddit without any notice to us,
+//! so every user is refreshed once every `MODERATOR_REFRESH_INTERVAL` seconds.
+//! Users can also refresh their own status on demand via `PATCH /v1/user/<id>/refresh`.
+//!
+//! Users that need to authenticate with Reddit again are skipped until they do so.
+
+use crate::{controller::User, DataPool};
+use lazy_static::lazy_static;
+use log::error;
+use std::{env, thread, time::Duration};
+
+lazy_static! {
+    /// The time between two refreshes of all users.
+    /// Defaults to one hour.
+    static ref REFRESH_INTERVAL: Duration = Duration::from_secs(
+        env::var("MODERATOR_REFRESH_INTERVAL")
+            .ok()
+            .and_then(|interval| interval.parse().ok())
+            .unwrap_or(3600)
+    );
+}
+
+/// Run the job, refreshing all users indefinitely.
+///
+/// A connection is only taken from the pool to read the users
+/// and to store each user's roles, never while waiting on Reddit.
+#[inline]
+pub fn spawn(pool: DataPool) {
+    loop {
+        let users = match pool.get().map(|conn| User::find_all(&conn)) {
+            Some(Ok(users)) => users,
+            Some(Err(err)) => {
+                error!("error loading users: {}", err);
+                vec![]
+            }
+            None => {
+                error!("unable to connect to the database to load users");
+                vec![]
+            }
+        };
+
+        for value in users.into_iter().filter(|value| !value.needs_reauth) {
+            let user_id = value.id;
+            let mut reddit_user = value.into();
+            let moderated = User::fetch_moderated_subreddits(&mut reddit_user);
+
+            let result = match pool.get() {
+                Some(conn) => {
+                    User::set_moderated_subreddits(&conn, user_id, &mut reddit_user, moderated)
+                }
+                None => Err("unable to connect to the database".into()),
+            };
+            if let Err(err) = result {
+                error!("error refreshing user {} from Reddit: {}", user_id, err);
+            }
+        }
+
+        thread::sleep(*REFRESH_INTERVAL);
+    }
+}
@@ -0,0 +1,128 @@
+//! A background queue for pushing `Thread`s to Reddit.
+//!
+//! Rather than editing the post on Reddit during every request that changes a thread,
+//! endpoints add the thread's ID to the queue and return immediately.
+//! A single worker then pushes pending threads,
+//! editing any given thread no more than once every `REDDIT_UPDATE_INTERVAL` seconds.
+//! Changes made while a thread is waiting are coalesced into a single edit,
+//! as the markdown is rendered at the time of the push.
+
+use crate::{controller::Thread, DataPool};
+use hashbrown::{HashMap, HashSet};
+use lazy_static::lazy_static;
+use log::error;
+use parking_lot::{Condvar, Mutex};
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+lazy_static! {
+    /// IDs of `Thread`s with changes that have yet to be pushed to Reddit.
+    static ref PENDING: Mutex<HashSet<i32>> = Mutex::new(HashSet::new());
+
+    /// The minimum time between two edits of the same thread.
+    /// Defaults to five seconds.
+    static ref UPDATE_INTERVAL: Duration = Duration::from_secs(
+        env::var("REDDIT_UPDATE_INTERVAL")
+            .ok()
+            .and_then(|interval| interval.parse().ok())
+            .unwrap_or(5)
+    );
+
+    /// Wakes the worker when a thread is added to the queue.
+    static ref WAKE: Condvar = Condvar::new();
+}
+
+/// Schedule a `Thread` to be updated on Reddit.
+///
+/// If an update is already pending,
+/// this is a no-op, as the latest markdown is always pushed.
+#[inline]
+pub fn enqueue(thread_id: i32) {
+    PENDING.lock().insert(thread_id);
+    WAKE.notify_one();
+}
+
+/// Does the `Thread` have changes that have yet to be pushed to Reddit?
+#[inline]
+pub fn is_pending(thread_id: i32) -> bool {
+    PENDING.lock().contains(&thread_id)
+}
+
+/// Wait until at least one pending thread may be pushed,
+/// removing and returning all such threads from the queue.
+#[inline]
+fn next_batch(last_pushed: &mut HashMap<i32, Instant>) -> Vec<i32> {
+    let mut pending = PENDING.lock();
+
+    loop {
+        let now = Instant::now();
+        last_pushed.retain(|_, pushed_at| now - *pushed_at < *UPDATE_INTERVAL);
+
+        let (due, waiting): (Vec<i32>, Vec<i32>) = pending
+            .iter()
+            .partition(|thread_id| !last_pushed.contains_key(thread_id));
+
+        if !due.is_empty() {
+            for thread_id in &due {
+                pending.remove(thread_id);
+            }
+            return due;
+        }
+
+        match waiting
+            .iter()
+            .map(|thread_id| last_pushed[thread_id] + *UPDATE_INTERVAL)
+            .min()
+        {
+            Some(next_due) => {
+                WAKE.wait_until(&mut pending, next_due);
+            }
+            None => WAKE.wait(&mut pending),
+        }
+    }
+}
+
+/// Run the worker, pushing queued threads to Reddit indefinitely.
+///
+/// A connection is only taken from the pool while a batch is being pushed.
+#[inline]
+pub fn spawn(pool: DataPool) {
+    let mut last_pushed = HashMap::new();
+
+    loop {
+        let batch = next_batch(&mut last_pushed);
+        let conn = match pool.get() {
+            Some(conn) => conn,
+            None => {
+                error!("unable to connect to the database to update threads on Reddit");
+                for thread_id in batch {
+                    last_pushed.insert(thread_id, Instant::now());
+                    enqueue(thread_id);
+                }
+                continue;
+            }
+        };
+
+        for thread_id in batch {
+            last_pushed.insert(thread_id, Instant::now());
+
+            let result = Thread::find_id(&conn, thread_id)
+                .map_err(Into::into)
+                .and_then(|thread| thread.update_on_reddit(&conn));
+
+            if let Err(err) = result {
+                error!("error updating thread {} on Reddit: {}", thread_id, err);
+
+                // Try again later if the failure was temporary.
+                if err
+                    .downcast_ref::<reddit::Error>()
+                    .map_or(false, reddit::Error::is_transient)
+                {
+                    enqueue(thread_id);
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,60 @@
+table! {
+    audit_log (id) {
+        id -> Int4,
+        actor_user_id -> Nullable<Int4>,
+        entity_type -> Varchar,
+        entity_id -> Int4,
+        thread_id -> Nullable<Int4>,
+        action -> Varchar,
+        before -> Nullable<Jsonb>,
+        after -> Nullable<Jsonb>,
+        created_at_utc -> Int8,
+    }
+}
+
+table! {
+    comment (id) {
+        id -> Int4,
+        in_thread_id -> Int4,
+        reddit_id -> Nullable<Varchar>,
+        sections_id -> Array<Int4>,
+        is_stickied -> Bool,
+        is_continuation -> Bool,
+        markdown -> Text,
+    }
+}
+
 table! {
     event (id) {
         id -> Int4,
         posted -> Bool,
         in_thread_id -> Int4,
         cols -> Jsonb,
+        deleted_at_utc -> Nullable<Int8>,
+        deleted_position -> Nullable<Int4>,
+        version -> Int4,
+        lock_held_by_user_id -> Nullable<Int4>,
+        lock_assigned_at_utc -> Int8,
+        lock_expires_at_utc -> Nullable<Int8>,
+    }
+}
+
+table! {
+    event_revision (id) {
+        id -> Int4,
+        event_id -> Int4,
+        posted -> Bool,
+        cols -> Jsonb,
+        created_by_user_id -> Nullable<Int4>,
+        created_at_utc -> Int8,
+    }
+}
+
+table! {
+    reddit_sync (thread_id) {
+        thread_id -> Int4,
+        markdown -> Text,
+        synced_at_utc -> Int8,
     }
 }
 
@@ -16,6 +67,21 @@ table! {
         lock_held_by_user_id -> Nullable<Int4>,
         in_thread_id -> Int4,
         lock_assigned_at_utc -> Int8,
+        deleted_at_utc -> Nullable<Int8>,
+        deleted_position -> Nullable<Int4>,
+        version -> Int4,
+        lock_expires_at_utc -> Nullable<Int8>,
+    }
+}
+
+table! {
+    section_revision (id) {
+        id -> Int4,
+        section_id -> Int4,
+        name -> Varchar,
+        content -> Text,
+        created_by_user_id -> Nullable<Int4>,
+        created_at_utc -> Int8,
     }
 }
 
@@ -35,6 +101,10 @@ table! {
         event_column_headers -> Array<Text>,
         space__utc_col_index -> Nullable<Int2>,
         is_live -> Bool,
+        flair_id -> Nullable<Varchar>,
+        flair_text -> Nullable<Varchar>,
+        deleted_at_utc -> Nullable<Int8>,
+        version -> Int4,
     }
 }
 
@@ -45,20 +115,44 @@ table! {
         lang -> Varchar,
         refresh_token -> Bytea,
         is_global_admin -> Bool,
-        spacex__is_host -> Bool,
-        spacex__is_mod -> Bool,
         spacex__is_slack_member -> Bool,
         access_token -> Bytea,
         access_token_expires_at_utc -> Int8,
+        needs_reauth -> Bool,
+    }
+}
+
+table! {
+    user_subreddit_role (id) {
+        id -> Int4,
+        user_id -> Int4,
+        subreddit -> Varchar,
+        role -> Varchar,
+        is_from_reddit -> Bool,
     }
 }
 
+joinable!(audit_log -> user (actor_user_id));
+joinable!(comment -> thread (in_thread_id));
+joinable!(event -> user (lock_held_by_user_id));
+joinable!(event_revision -> event (event_id));
+joinable!(event_revision -> user (created_by_user_id));
+joinable!(reddit_sync -> thread (thread_id));
 joinable!(section -> user (lock_held_by_user_id));
+joinable!(section_revision -> section (section_id));
+joinable!(section_revision -> user (created_by_user_id));
 joinable!(thread -> user (created_by_user_id));
+joinable!(user_subreddit_role -> user (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    audit_log,
+    comment,
     event,
+    event_revision,
+    reddit_sync,
     section,
+    section_revision,
     thread,
     user,
+    user_subreddit_role,
 );
@@ -0,0 +1,108 @@
+use crate::{guid, tests::helpers::*};
+use serde_json::json;
+
+const BASE: &str = "/v1/audit";
+
+#[test]
+fn get_by_thread() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let data = json!({ "video_url": guid() });
+    client
+        .with_base("/v1/thread")
+        .patch(Some(&user_token), thread_id, &data)
+        .assert_ok();
+
+    // test
+    let body = client
+        .with_base(BASE)
+        .get_authenticated(&user_token, format!("?thread_id={}", thread_id))
+        .assert_ok()
+        .get_body_array();
+    let entries = body.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0]["action"], "update");
+    assert_eq!(entries[0]["entity_type"], "thread");
+    assert_eq!(entries[0]["entity_id"], thread_id);
+    assert_eq!(entries[0]["actor_user_id"], user_id);
+    assert_eq!(
+        entries[0]["before"],
+        json!({ "video_url": null, "version": 1 })
+    );
+    assert_eq!(
+        entries[0]["after"],
+        json!({ "video_url": data["video_url"], "version": 2 })
+    );
+
+    assert_eq!(entries[1]["action"], "create");
+    assert_eq!(entries[1]["before"], json!(null));
+    assert_eq!(entries[1]["after"]["id"], thread_id);
+
+    // teardown
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn get_by_user() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    thread::delete(&mut client, &user_token, thread_id);
+
+    // test
+    let body = client
+        .with_base(BASE)
+        .get_authenticated(&admin_token, format!("?user_id={}", user_id))
+        .assert_ok()
+        .get_body_array();
+    let actions: Vec<_> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["delete", "create"]);
+
+    // teardown
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+#[should_panic]
+fn get_all_not_admin() {
+    let mut client = Client::new();
+    let (_, user_token) = user::create(&mut client);
+
+    client
+        .with_base(BASE)
+        .get_authenticated(&user_token, "")
+        .assert_ok();
+}
+
+#[test]
+fn get_limit_out_of_range() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+
+    // test
+    for limit in &[-1, 0, 1001] {
+        client
+            .with_base(BASE)
+            .get_authenticated(&admin_token, format!("?limit={}", limit))
+            .assert_bad_request();
+    }
+
+    // teardown
+    user::delete(&mut client, admin_id);
+}
@@ -0,0 +1,185 @@
+use crate::tests::helpers::*;
+use serde_json::{json, Value as Json};
+
+const BASE: &str = "/v1/comment";
+
+fn create_section(client: &mut Client<'_>, token: &str, thread_id: i32) -> i32 {
+    client
+        .with_base("/v1/section")
+        .post(Some(token), json!({ "in_thread_id": thread_id }))
+        .assert_created()
+        .get_body_object()["id"]
+        .as_i64()
+        .unwrap() as i32
+}
+
+fn create_comment(client: &mut Client<'_>, token: &str, thread_id: i32) -> Json {
+    client
+        .with_base(BASE)
+        .post(Some(token), json!({ "in_thread_id": thread_id }))
+        .assert_created()
+        .get_body_object()
+}
+
+#[test]
+fn get_all() {
+    Client::new()
+        .with_base(BASE)
+        .get_all()
+        .assert_ok()
+        .get_body_array();
+}
+
+#[test]
+fn get_one() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_comment(&mut client, &user_token, thread_id);
+
+    // test
+    let body = client
+        .with_base(BASE)
+        .get(&created_value["id"])
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(created_value, body);
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn create() {
+    let mut client = Client::new();
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let section_id = create_section(&mut client, &user_token, thread_id);
+
+    let comment = json!({
+        "in_thread_id": thread_id,
+        "sections_id": [section_id],
+    });
+
+    let mut body = client
+        .with_base(BASE)
+        .post(Some(&user_token), &comment)
+        .assert_created()
+        .get_body_object();
+    assert!(body["id"].is_number(), r#"body["id"] is number"#);
+
+    // store this so we can perform the teardown
+    let id = body["id"].as_i64().unwrap();
+
+    // Remove this, as we don't know what value we should expect.
+    // Afterwards, we can ensure that the value is null.
+    body["id"].take();
+    assert_eq!(
+        body,
+        json!({
+            // auto-generated
+            "id": null,
+            "reddit_id": null,
+            "is_stickied": false,
+            "is_continuation": false,
+            "markdown": "",
+
+            // user-provided
+            "in_thread_id": thread_id,
+            "sections_id": [section_id],
+        })
+    );
+
+    // teardown
+    client.with_base(BASE).delete(Some(&user_token), id);
+    client
+        .with_base("/v1/section")
+        .delete(Some(&user_token), section_id);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+#[should_panic]
+fn create_section_in_other_thread() {
+    let mut client = Client::new();
+    let (_, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let other_thread_id = thread::create(&mut client, &user_token);
+    let section_id = create_section(&mut client, &user_token, other_thread_id);
+
+    client
+        .with_base(BASE)
+        .post(
+            Some(&user_token),
+            json!({
+                "in_thread_id": thread_id,
+                "sections_id": [section_id],
+            }),
+        )
+        .assert_created();
+}
+
+#[test]
+fn update() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let section_id = create_section(&mut client, &user_token, thread_id);
+    let created_value = create_comment(&mut client, &user_token, thread_id);
+    assert_eq!(created_value["sections_id"], json!([]));
+
+    // test
+    let data = json!({ "sections_id": [section_id] });
+    let body = client
+        .with_base(BASE)
+        .patch(Some(&user_token), &created_value["id"], &data)
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["sections_id"], data["sections_id"]);
+
+    // Deleting the section should remove it from the comment.
+    client
+        .with_base("/v1/section")
+        .delete(Some(&user_token), section_id)
+        .assert_no_content();
+    let body = client
+        .with_base(BASE)
+        .get(&created_value["id"])
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["sections_id"], json!([]));
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn delete() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_comment(&mut client, &user_token, thread_id);
+
+    // test
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"])
+        .assert_no_content();
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
@@ -82,6 +82,12 @@ fn create() {
             "posted": false,
             "cols": event["cols"],
             "in_thread_id": event["in_thread_id"],
+            "deleted_at_utc": null,
+            "deleted_position": null,
+            "version": 1,
+            "lock_held_by_user_id": null,
+            "lock_assigned_at_utc": 0,
+            "lock_expires_at_utc": null,
         })
     );
 
@@ -128,6 +134,125 @@ fn update() {
     user::delete(&mut client, user_id);
 }
 
+#[test]
+fn update_locked() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_event(&mut client, &user_token, thread_id);
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "lock_held_by_user_id": user_id }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["lock_held_by_user_id"], user_id);
+    assert!(body["lock_expires_at_utc"].is_number());
+
+    // test
+    let data = json!({ "posted": true });
+    client
+        .with_base(BASE)
+        .patch(Some(&admin_token), &created_value["id"], &data)
+        .assert_conflict();
+    client
+        .with_base(BASE)
+        .delete(Some(&admin_token), &created_value["id"])
+        .assert_conflict();
+
+    // Admins may not hand a lock held by someone else to another user.
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&admin_token),
+            &created_value["id"],
+            json!({ "lock_held_by_user_id": user_id }),
+        )
+        .assert_forbidden();
+
+    // The lock holder is still able to make changes.
+    client
+        .with_base(BASE)
+        .patch(Some(&user_token), &created_value["id"], &data)
+        .assert_ok();
+
+    // Admins may break the lock, after which they can make changes.
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&admin_token),
+            &created_value["id"],
+            json!({ "lock_held_by_user_id": null }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["lock_held_by_user_id"], json!(null));
+    assert_eq!(body["lock_expires_at_utc"], json!(null));
+    client
+        .with_base(BASE)
+        .delete(Some(&admin_token), &created_value["id"])
+        .assert_no_content();
+
+    // teardown
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+fn restore() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_event(&mut client, &user_token, thread_id);
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!([[2, guid()]]),
+        )
+        .assert_ok();
+
+    // test
+    let revisions = client
+        .with_base(BASE)
+        .get(format!("{}/revisions", created_value["id"]))
+        .assert_ok()
+        .get_body_array();
+    assert_eq!(revisions.as_array().unwrap().len(), 2);
+    assert_eq!(revisions[1]["cols"], created_value["cols"]);
+
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            format!(
+                "{}/revisions/{}/restore",
+                created_value["id"], revisions[1]["id"]
+            ),
+            json!({}),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["cols"], created_value["cols"]);
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
 #[test]
 fn delete() {
     let mut client = Client::new();
@@ -145,3 +270,37 @@ fn delete() {
     thread::delete(&mut client, &user_token, thread_id);
     user::delete(&mut client, user_id);
 }
+
+#[test]
+fn delete_if_match() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_event(&mut client, &user_token, thread_id);
+    let etag = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "posted": true }),
+        )
+        .assert_ok()
+        .get_etag();
+
+    // test
+    client
+        .with_base(BASE)
+        .delete_if_match(&user_token, &created_value["id"], r#""1""#)
+        .assert_precondition_failed();
+    client.with_base(BASE).get(&created_value["id"]).assert_ok();
+    client
+        .with_base(BASE)
+        .delete_if_match(&user_token, &created_value["id"], &etag)
+        .assert_no_content();
+
+    // teardown
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
@@ -4,6 +4,14 @@ use rocket::{
     local::{Client as RocketClient, LocalResponse as RocketResponse},
 };
 use serde_json::Value;
+use std::{
+    io::{self, Read},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long to wait for a server-sent event before failing.
+const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct Client<'a> {
     base:   &'a str,
@@ -40,6 +48,28 @@ impl<'a> Client<'a> {
         Response(self.client.get(self.url_for(id)).dispatch())
     }
 
+    #[inline]
+    pub fn get_authenticated(&self, token: &str, id: impl ToString) -> Response<'_> {
+        Response(
+            self.client
+                .get(self.url_for(id))
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .dispatch(),
+        )
+    }
+
+    /// Open a stream of server-sent events for the comma-separated rooms.
+    #[inline]
+    pub fn stream(&self, rooms: &str, last_event_id: Option<u64>) -> Response<'_> {
+        let request = self.client.get(format!("/v1/stream?rooms={}", rooms));
+        Response(match last_event_id {
+            Some(id) => request
+                .header(Header::new("Last-Event-ID", id.to_string()))
+                .dispatch(),
+            None => request.dispatch(),
+        })
+    }
+
     #[inline]
     pub fn post(&self, token: Option<&str>, body: impl ToString) -> Response<'_> {
         Response(match token {
@@ -79,6 +109,24 @@ impl<'a> Client<'a> {
         })
     }
 
+    #[inline]
+    pub fn patch_if_match(
+        &self,
+        token: &str,
+        id: impl ToString,
+        etag: &str,
+        body: impl ToString,
+    ) -> Response<'_> {
+        Response(
+            self.client
+                .patch(self.url_for(id))
+                .body(body.to_string())
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .header(Header::new("If-Match", etag.to_owned()))
+                .dispatch(),
+        )
+    }
+
     #[inline]
     pub fn delete(&self, token: Option<&str>, id: impl ToString) -> Response<'_> {
         Response(match token {
@@ -90,6 +138,17 @@ impl<'a> Client<'a> {
             None => self.client.delete(self.url_for(id)).dispatch(),
         })
     }
+
+    #[inline]
+    pub fn delete_if_match(&self, token: &str, id: impl ToString, etag: &str) -> Response<'_> {
+        Response(
+            self.client
+                .delete(self.url_for(id))
+                .header(Header::new("Authorization", format!("Bearer {}", token)))
+                .header(Header::new("If-Match", etag.to_owned()))
+                .dispatch(),
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -118,6 +177,42 @@ impl Response<'_> {
         self
     }
 
+    #[inline]
+    pub fn assert_not_found(self) -> Self {
+        assert_eq!(self.status(), Status::NotFound);
+        self
+    }
+
+    #[inline]
+    pub fn assert_precondition_failed(self) -> Self {
+        assert_eq!(self.status(), Status::PreconditionFailed);
+        self
+    }
+
+    #[inline]
+    pub fn assert_forbidden(self) -> Self {
+        assert_eq!(self.status(), Status::Forbidden);
+        self
+    }
+
+    #[inline]
+    pub fn assert_conflict(self) -> Self {
+        assert_eq!(self.status(), Status::Conflict);
+        self
+    }
+
+    #[inline]
+    pub fn assert_bad_request(self) -> Self {
+        assert_eq!(self.status(), Status::BadRequest);
+        self
+    }
+
+    #[inline]
+    pub fn assert_unauthorized(self) -> Self {
+        assert_eq!(self.status(), Status::Unauthorized);
+        self
+    }
+
     #[inline]
     pub fn assert_see_other(self) -> Self {
         assert_eq!(self.status(), Status::SeeOther);
@@ -129,6 +224,11 @@ impl Response<'_> {
         self.0.headers().get_one("Location").unwrap().into()
     }
 
+    #[inline]
+    pub fn get_etag(&self) -> String {
+        self.0.headers().get_one("ETag").unwrap().into()
+    }
+
     #[inline]
     pub fn get_body_array(mut self) -> Value {
         let body = self.body();
@@ -143,6 +243,33 @@ impl Response<'_> {
         body
     }
 
+    /// Read the next server-sent event from a streaming body, skipping comments.
+    #[inline]
+    pub fn next_event(&mut self) -> String {
+        let start = Instant::now();
+        let body = self.0.body().expect("body is present").into_inner();
+        let mut event = vec![];
+        let mut byte = [0];
+
+        while !event.ends_with(b"\n\n") {
+            match body.read(&mut byte) {
+                Ok(0) => panic!("stream ended"),
+                Ok(_) => event.push(byte[0]),
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    assert!(start.elapsed() < EVENT_TIMEOUT, "no event received");
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(err) => panic!("{}", err),
+            }
+
+            if event == b":\n\n" {
+                event.clear();
+            }
+        }
+
+        String::from_utf8(event).unwrap()
+    }
+
     #[inline]
     fn body(&mut self) -> Value {
         self.0
@@ -1,6 +1,8 @@
 mod client;
+mod websocket;
 
 pub mod thread;
 pub mod user;
 
 pub use client::Client;
+pub use websocket::WebSocket;
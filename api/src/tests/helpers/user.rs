@@ -28,6 +28,19 @@ pub fn create(client: &mut Client<'_>) -> (i32, String) {
     )
 }
 
+pub fn create_admin(client: &mut Client<'_>) -> (i32, String) {
+    create_with_body(
+        client,
+        json!({
+            "reddit_username": guid(),
+            "refresh_token": guid(),
+            "access_token": guid(),
+            "access_token_expires_at_utc": 0,
+            "is_global_admin": true,
+        }),
+    )
+}
+
 pub fn delete(client: &mut Client<'_>, id: i32) {
     client.with_base(BASE).delete(None, id);
 }
@@ -0,0 +1,113 @@
+use crate::{websocket, DataDB};
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::{
+    net::SocketAddr,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+use ws::{CloseCode, Handler, Handshake, Message, Sender};
+
+/// How long to wait for a message before failing.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    /// The address of a server shared by all tests,
+    /// bound to any available port so as not to conflict with a running instance.
+    static ref ADDR: SocketAddr = {
+        let (addr_tx, addr_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let conn = DataDB::get_one(&crate::server()).unwrap();
+            let server = websocket::bind("127.0.0.1:0", conn).unwrap();
+            addr_tx.send(server.local_addr().unwrap()).unwrap();
+            server.run().unwrap();
+        });
+        addr_rx.recv().unwrap()
+    };
+}
+
+/// Forwards the connection and all received messages to the test.
+struct Listener {
+    out:      Sender,
+    opened:   Option<mpsc::Sender<Sender>>,
+    messages: mpsc::Sender<Value>,
+}
+
+impl Handler for Listener {
+    #[inline]
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        if let Some(opened) = self.opened.take() {
+            let _ = opened.send(self.out.clone());
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn on_message(&mut self, message: Message) -> ws::Result<()> {
+        let _ = self
+            .messages
+            .send(serde_json::from_str(&message.into_text()?).unwrap());
+        Ok(())
+    }
+}
+
+pub struct WebSocket {
+    out:      Sender,
+    messages: Receiver<Value>,
+}
+
+impl WebSocket {
+    #[inline]
+    pub fn connect() -> Self {
+        Self::connect_to(format!("ws://{}", *ADDR))
+    }
+
+    /// Connect, authenticating with the token in the query string.
+    /// Panics if the server rejects the connection.
+    #[inline]
+    pub fn connect_authenticated(token: &str) -> Self {
+        Self::connect_to(format!("ws://{}/?token={}", *ADDR, token))
+    }
+
+    #[inline]
+    fn connect_to(url: String) -> Self {
+        let (opened_tx, opened_rx) = mpsc::channel();
+        let (messages_tx, messages_rx) = mpsc::channel();
+
+        // A rejected connection drops the sender, failing the test below.
+        thread::spawn(move || {
+            let _ = ws::connect(url, |out| Listener {
+                out,
+                opened: Some(opened_tx.clone()),
+                messages: messages_tx.clone(),
+            });
+        });
+
+        WebSocket {
+            out:      opened_rx.recv_timeout(TIMEOUT).expect("unable to connect"),
+            messages: messages_rx,
+        }
+    }
+
+    #[inline]
+    pub fn send(&self, message: impl ToString) -> &Self {
+        self.out.send(message.to_string()).unwrap();
+        self
+    }
+
+    /// Wait for the next message, failing if none arrives.
+    #[inline]
+    pub fn recv(&self) -> Value {
+        self.messages
+            .recv_timeout(TIMEOUT)
+            .expect("no message received")
+    }
+}
+
+impl Drop for WebSocket {
+    #[inline]
+    fn drop(&mut self) {
+        let _ = self.out.close(CloseCode::Normal);
+    }
+}
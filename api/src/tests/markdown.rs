@@ -1,5 +1,5 @@
 use crate::{
-    controller::{Event, Section, Thread, ToMarkdown},
+    controller::{Event, Overflow, OverflowStrategy, Section, Thread, ToMarkdown},
     server,
     tests::helpers::*,
     DataDB,
@@ -55,6 +55,13 @@ fn event_unposted() -> Result<(), Box<dyn Error>> {
         posted:       false,
         cols:         json!([1_546_305_060, "T+0:00", "foo"]),
         in_thread_id: 0, // irrelevant
+        version:      1,
+
+        deleted_at_utc:       None,
+        deleted_position:     None,
+        lock_held_by_user_id: None,
+        lock_assigned_at_utc: 0,
+        lock_expires_at_utc:  None,
     };
 
     let md = event.to_markdown(&database)?;
@@ -75,6 +82,10 @@ fn section_not_events() -> Result<(), Box<dyn Error>> {
         lock_held_by_user_id: None,
         lock_assigned_at_utc: 0,
         in_thread_id: 0,
+        deleted_at_utc: None,
+        deleted_position: None,
+        version: 1,
+        lock_expires_at_utc: None,
     };
 
     let md = section.to_markdown(&database)?;
@@ -275,3 +286,165 @@ fn thread() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn thread_overflow() -> Result<(), Box<dyn Error>> {
+    // setup
+    let mut client = Client::new();
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+
+    let sections_id: Vec<_> = vec![
+        json!({
+            "in_thread_id": thread_id,
+            "name": "Introduction",
+            "content": "Sed consectetur nunc molestie eros.",
+            "is_events_section": false,
+        }),
+        json!({
+            "in_thread_id": thread_id,
+            "name": "Live Updates",
+            "content": "",
+            "is_events_section": true,
+        }),
+        json!({
+            "in_thread_id": thread_id,
+            "name": "Participate!",
+            "content": "Fusce volutpat nisl a metus.",
+            "is_events_section": false,
+        }),
+    ]
+    .iter()
+    .map(|section| {
+        client
+            .with_base("/v1/section")
+            .post(Some(&user_token), section)
+            .assert_created()
+            .get_body_object()["id"]
+            .as_i64()
+            .unwrap() as i32
+    })
+    .collect();
+
+    let events_id: Vec<_> = vec![
+        json!({
+            "posted": true,
+            "cols": [1_546_305_060, "T+0:00", "foo"],
+            "in_thread_id": thread_id,
+        }),
+        json!({
+            "posted": true,
+            "cols": [1_546_305_120, "T+1:00", "baz"],
+            "in_thread_id": thread_id,
+        }),
+    ]
+    .iter()
+    .map(|event| {
+        client
+            .with_base("/v1/event")
+            .post(Some(&user_token), event)
+            .assert_created()
+            .get_body_object()["id"]
+            .as_i64()
+            .unwrap() as i32
+    })
+    .collect();
+
+    let conn = crate::DataDB::get_one(&crate::server()).unwrap();
+    let thread = Thread::find_id(&conn, thread_id)?;
+
+    // test
+    // The full thread is 189 characters.
+    let rendered = thread.render_within(&conn, 189, OverflowStrategy::TruncateEvents)?;
+    assert_eq!(rendered.markdown, thread.to_markdown(&conn)?);
+    assert_eq!(rendered.overflow, None);
+
+    let rendered = thread.render_within(&conn, 180, OverflowStrategy::TruncateEvents)?;
+    assert_eq!(
+        "# Introduction\n\
+         Sed consectetur nunc molestie eros.\n\
+         \n\
+         # Live Updates\n\
+         |UTC|Countdown|Update|\n\
+         |---|---|---|\n\
+         |01:12|T+1:00|baz|\n\
+         \n\
+         \n\
+         # Participate!\n\
+         Fusce volutpat nisl a metus.\n\
+         \n\
+         ",
+        rendered.markdown
+    );
+    assert_eq!(
+        rendered.overflow,
+        Some(Overflow::TruncateEvents { omitted_events: 1 })
+    );
+
+    let rendered = thread.render_within(&conn, 180, OverflowStrategy::CollapseSections)?;
+    assert_eq!(
+        "# Introduction\n\
+         Sed consectetur nunc molestie eros.\n\
+         \n\
+         # Live Updates\n\
+         *Omitted due to length.*\n\
+         \n\
+         # Participate!\n\
+         Fusce volutpat nisl a metus.\n\
+         \n\
+         ",
+        rendered.markdown
+    );
+    assert_eq!(
+        rendered.overflow,
+        Some(Overflow::CollapseSections {
+            collapsed_sections_id: vec![sections_id[1]],
+        })
+    );
+
+    let rendered = thread.render_within(&conn, 180, OverflowStrategy::Continuation)?;
+    assert_eq!(
+        "# Introduction\n\
+         Sed consectetur nunc molestie eros.\n\
+         \n\
+         # Live Updates\n\
+         |UTC|Countdown|Update|\n\
+         |---|---|---|\n\
+         |01:11|T+0:00|foo|\n\
+         |01:12|T+1:00|baz|\n\
+         \n\
+         \n\
+         *Continued in the comments.*\n\
+         \n\
+         ",
+        rendered.markdown
+    );
+    assert_eq!(
+        rendered.overflow,
+        Some(Overflow::Continuation {
+            continued_sections_id: vec![sections_id[2]],
+        })
+    );
+
+    let rendered = thread.render_within(&conn, 50, OverflowStrategy::TruncateEvents)?;
+    assert_eq!("# Introduction\n", rendered.markdown);
+    assert_eq!(rendered.overflow, Some(Overflow::Truncate));
+
+    // teardown
+    for id in events_id {
+        client
+            .with_base("/v1/event")
+            .delete(Some(&user_token), id)
+            .assert_no_content();
+    }
+    for id in sections_id {
+        client
+            .with_base("/v1/section")
+            .delete(Some(&user_token), id)
+            .assert_no_content();
+    }
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+
+    Ok(())
+}
@@ -1,8 +1,13 @@
+mod audit;
+mod comment;
 mod event;
 mod helpers;
 mod markdown;
 mod meta;
 mod oauth;
 mod section;
+mod stream;
 mod thread;
 mod user;
+mod user_subreddit_role;
+mod websocket;
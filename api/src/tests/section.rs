@@ -1,4 +1,4 @@
-use crate::{guid, tests::helpers::*};
+use crate::{controller::Section, guid, server, tests::helpers::*, DataDB};
 use serde_json::{json, Value as Json};
 
 const BASE: &str = "/v1/section";
@@ -81,6 +81,10 @@ fn create() {
             "lock_held_by_user_id": null,
             "lock_assigned_at_utc": null,
             "in_thread_id": section["in_thread_id"],
+            "deleted_at_utc": null,
+            "deleted_position": null,
+            "version": 1,
+            "lock_expires_at_utc": null,
         })
     );
 
@@ -117,6 +121,248 @@ fn update() {
     user::delete(&mut client, user_id);
 }
 
+#[test]
+fn update_if_match() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_section(&mut client, &user_token, thread_id);
+    let etag = client
+        .with_base(BASE)
+        .get(&created_value["id"])
+        .assert_ok()
+        .get_etag();
+    assert_eq!(etag, r#""1""#);
+
+    // test
+    let response = client
+        .with_base(BASE)
+        .patch_if_match(
+            &user_token,
+            &created_value["id"],
+            &etag,
+            json!({ "name": guid() }),
+        )
+        .assert_ok();
+    assert_eq!(response.get_etag(), r#""2""#);
+    assert_eq!(response.get_body_object()["version"], 2);
+
+    // The section has since been updated, so the stale version is rejected.
+    let data = json!({ "name": guid() });
+    client
+        .with_base(BASE)
+        .patch_if_match(&user_token, &created_value["id"], &etag, &data)
+        .assert_precondition_failed();
+    let body = client
+        .with_base(BASE)
+        .get(&created_value["id"])
+        .assert_ok()
+        .get_body_object();
+    assert_ne!(body["name"], data["name"]);
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn update_locked() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_section(&mut client, &user_token, thread_id);
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "lock_held_by_user_id": user_id }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(
+        body["lock_expires_at_utc"].as_i64(),
+        body["lock_assigned_at_utc"]
+            .as_i64()
+            .map(|assigned_at| assigned_at + 600)
+    );
+    // Setting the lock increments the version.
+    assert_eq!(body["version"], 2);
+
+    // test
+    let data = json!({ "name": guid() });
+    client
+        .with_base(BASE)
+        .patch(Some(&admin_token), &created_value["id"], &data)
+        .assert_conflict();
+    client
+        .with_base(BASE)
+        .delete(Some(&admin_token), &created_value["id"])
+        .assert_conflict();
+
+    // Admins may not hand a lock held by someone else to another user.
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&admin_token),
+            &created_value["id"],
+            json!({ "lock_held_by_user_id": user_id }),
+        )
+        .assert_forbidden();
+
+    // The lock holder is still able to make changes.
+    client
+        .with_base(BASE)
+        .patch(Some(&user_token), &created_value["id"], &data)
+        .assert_ok();
+
+    // Admins may break the lock, after which they can make changes.
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&admin_token),
+            &created_value["id"],
+            json!({ "lock_held_by_user_id": null }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["lock_held_by_user_id"], json!(null));
+    assert_eq!(body["lock_expires_at_utc"], json!(null));
+    assert_eq!(body["version"], 4);
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&admin_token),
+            &created_value["id"],
+            json!({ "name": guid() }),
+        )
+        .assert_ok();
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+fn release_expired_lock() {
+    let mut client = Client::new();
+    let conn = DataDB::get_one(&server()).unwrap();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_section(&mut client, &user_token, thread_id);
+    let section_id = created_value["id"].as_i64().unwrap() as i32;
+    let expires_at = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            section_id,
+            json!({ "lock_held_by_user_id": user_id }),
+        )
+        .assert_ok()
+        .get_body_object()["lock_expires_at_utc"]
+        .as_i64()
+        .unwrap();
+
+    // test
+    // A lock that hasn't expired yet is left alone.
+    let result = Section::release_expired_lock(&conn, section_id, expires_at - 1).unwrap();
+    assert!(result.is_none());
+    let body = client
+        .with_base(BASE)
+        .get(section_id)
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["lock_held_by_user_id"], user_id);
+
+    let result = Section::release_expired_lock(&conn, section_id, expires_at).unwrap();
+    assert_eq!(result.map(|value| value.lock_held_by_user_id), Some(None));
+
+    // teardown
+    client.with_base(BASE).delete(Some(&user_token), section_id);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn restore() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let created_value = create_section(&mut client, &user_token, thread_id);
+    let content = guid();
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "content": content }),
+        )
+        .assert_ok();
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "content": "" }),
+        )
+        .assert_ok();
+
+    // test
+    let revisions = client
+        .with_base(BASE)
+        .get(format!("{}/revisions", created_value["id"]))
+        .assert_ok()
+        .get_body_array();
+    assert_eq!(revisions.as_array().unwrap().len(), 3);
+    assert_eq!(revisions[0]["content"], "");
+    assert_eq!(revisions[1]["content"], content);
+
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            format!(
+                "{}/revisions/{}/restore",
+                created_value["id"], revisions[1]["id"]
+            ),
+            json!({}),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["content"], content);
+
+    let revisions = client
+        .with_base(BASE)
+        .get(format!("{}/revisions", created_value["id"]))
+        .assert_ok()
+        .get_body_array();
+    assert_eq!(revisions.as_array().unwrap().len(), 4);
+    assert_eq!(revisions[0]["content"], content);
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
 #[test]
 fn delete() {
     let mut client = Client::new();
@@ -134,3 +380,115 @@ fn delete() {
     thread::delete(&mut client, &user_token, thread_id);
     user::delete(&mut client, user_id);
 }
+
+#[test]
+fn undelete() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let sections_id: Vec<_> = (0..3)
+        .map(|_| create_section(&mut client, &user_token, thread_id)["id"].clone())
+        .collect();
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &sections_id[1])
+        .assert_no_content();
+
+    // test
+    client
+        .with_base(BASE)
+        .get(&sections_id[1])
+        .assert_not_found();
+    let body = client
+        .with_base("/v1/thread")
+        .get(thread_id)
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["sections_id"], json!([sections_id[0], sections_id[2]]));
+
+    let body = client
+        .with_base(BASE)
+        .get_authenticated(
+            &admin_token,
+            format!("{}?include_deleted=true", sections_id[1]),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert!(body["deleted_at_utc"].is_number());
+    assert_eq!(body["deleted_position"], 1);
+
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            format!("{}/undelete", sections_id[1]),
+            json!({}),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["deleted_at_utc"], json!(null));
+
+    let body = client
+        .with_base("/v1/thread")
+        .get(thread_id)
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["sections_id"], json!(sections_id));
+
+    // teardown
+    for section_id in &sections_id {
+        client.with_base(BASE).delete(Some(&user_token), section_id);
+    }
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+#[should_panic]
+fn get_deleted_not_admin() {
+    let mut client = Client::new();
+    let (_, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let section_id = create_section(&mut client, &user_token, thread_id)["id"].clone();
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &section_id);
+
+    client
+        .with_base(BASE)
+        .get_authenticated(&user_token, format!("{}?include_deleted=true", section_id))
+        .assert_ok();
+}
+
+#[test]
+fn create_in_deleted_thread() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let thread_id = thread::create(&mut client, &admin_token);
+    thread::delete(&mut client, &admin_token, thread_id);
+
+    // test
+    client
+        .with_base(BASE)
+        .post(Some(&admin_token), json!({ "in_thread_id": thread_id }))
+        .assert_not_found();
+    let body = client
+        .with_base(BASE)
+        .get_authenticated(&admin_token, "?include_deleted=true")
+        .assert_ok()
+        .get_body_array();
+    assert!(body
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|value| value["in_thread_id"] != thread_id));
+
+    // teardown
+    user::delete(&mut client, admin_id);
+}
@@ -0,0 +1,120 @@
+use crate::{guid, tests::helpers::*};
+use serde_json::{json, Value as Json};
+
+/// Split a server-sent event into its ID and data.
+fn parse_event(event: &str) -> (u64, Json) {
+    let mut lines = event.lines();
+    let id = lines
+        .next()
+        .unwrap()
+        .trim_start_matches("id: ")
+        .parse()
+        .unwrap();
+    let data = serde_json::from_str(lines.next().unwrap().trim_start_matches("data: ")).unwrap();
+    (id, data)
+}
+
+fn create_section(client: &mut Client<'_>, token: &str, thread_id: i32) -> Json {
+    client
+        .with_base("/v1/section")
+        .post(Some(token), json!({ "in_thread_id": thread_id }))
+        .assert_created()
+        .get_body_object()["id"]
+        .clone()
+}
+
+#[test]
+fn receive_updates() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let section_id = create_section(&mut client, &user_token, thread_id);
+    let room = format!("section_{}", section_id);
+
+    // test
+    let stream_client = Client::new();
+    let mut stream = stream_client.stream(&room, None).assert_ok();
+    let data = json!({ "name": guid() });
+    client
+        .with_base("/v1/section")
+        .patch(Some(&user_token), &section_id, &data)
+        .assert_ok();
+
+    let (id, message) = parse_event(&stream.next_event());
+    assert_eq!(message["room"], room);
+    assert_eq!(message["seq"], id);
+    assert_eq!(message["action"], "update");
+    assert_eq!(message["data_type"], "section");
+    assert_eq!(message["data"]["id"], section_id);
+    assert_eq!(message["data"]["name"], data["name"]);
+    drop(stream);
+
+    // teardown
+    client
+        .with_base("/v1/section")
+        .delete(Some(&user_token), &section_id);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn replay() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let section_id = create_section(&mut client, &user_token, thread_id);
+    let room = format!("section_{}", section_id);
+
+    // The stream borrows its client for as long as it is open.
+    let stream_client = Client::new();
+    let mut stream = stream_client.stream(&room, None).assert_ok();
+    client
+        .with_base("/v1/section")
+        .patch(Some(&user_token), &section_id, json!({ "name": guid() }))
+        .assert_ok();
+    let (last_event_id, _) = parse_event(&stream.next_event());
+    drop(stream);
+
+    // test
+    let names = [guid(), guid()];
+    for name in names.iter() {
+        client
+            .with_base("/v1/section")
+            .patch(Some(&user_token), &section_id, json!({ "name": name }))
+            .assert_ok();
+    }
+
+    let mut stream = stream_client.stream(&room, Some(last_event_id)).assert_ok();
+    for name in names.iter() {
+        let (_, message) = parse_event(&stream.next_event());
+        assert_eq!(message["data"]["name"], *name);
+    }
+    drop(stream);
+
+    // A stream unable to catch up is told to resync.
+    let mut stream = stream_client.stream("thread_create", Some(0)).assert_ok();
+    assert_eq!(
+        stream.next_event(),
+        "event: resync\ndata: {\"room\":\"thread_create\"}\n\n"
+    );
+    drop(stream);
+
+    // teardown
+    client
+        .with_base("/v1/section")
+        .delete(Some(&user_token), &section_id);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn invalid_rooms() {
+    let client = Client::new();
+
+    client.stream("thread_1,foo", None).assert_bad_request();
+    client.stream("user", None).assert_unauthorized();
+}
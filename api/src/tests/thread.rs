@@ -61,6 +61,7 @@ fn create() {
         "space__t0": rand::random::<i64>(),
         "video_url": guid(),
         "event_column_headers": [],
+        "flair_text": "Launch",
     });
 
     let mut body = client
@@ -87,12 +88,16 @@ fn create() {
             "sections_id": [],
             "events_id": [],
             "is_live": false,
+            "flair_id": null,
+            "deleted_at_utc": null,
+            "version": 1,
 
             // user-provided
             "thread_name": thread["thread_name"],
             "display_name": thread["display_name"],
             "video_url": thread["video_url"],
             "event_column_headers": thread["event_column_headers"],
+            "flair_text": thread["flair_text"],
         })
     );
 
@@ -139,6 +144,105 @@ fn update() {
     user::delete(&mut client, user_id);
 }
 
+#[test]
+fn update_reorder_only() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let created_value = create_thread(&mut client, &user_token);
+    let mut events_id = vec![];
+    for _ in 0..2 {
+        events_id.push(
+            client
+                .with_base("/v1/event")
+                .post(
+                    Some(&user_token),
+                    json!({ "in_thread_id": created_value["id"], "cols": [] }),
+                )
+                .assert_created()
+                .get_body_object()["id"]
+                .clone(),
+        );
+    }
+
+    // test
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "events_id": [events_id[0]] }),
+        )
+        .assert_precondition_failed();
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "sections_id": [0] }),
+        )
+        .assert_precondition_failed();
+
+    events_id.reverse();
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "events_id": events_id }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["events_id"], json!(events_id));
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn update_flair() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let created_value = create_thread(&mut client, &user_token);
+
+    // test
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "flair_id": guid() }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert!(body["flair_id"].is_string());
+
+    // Setting the flair's text alone clears any previous template.
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            &created_value["id"],
+            json!({ "flair_text": "Launch" }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["flair_id"], json!(null));
+    assert_eq!(body["flair_text"], "Launch");
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    user::delete(&mut client, user_id);
+}
+
 #[test]
 fn delete() {
     let mut client = Client::new();
@@ -154,3 +258,161 @@ fn delete() {
         .assert_no_content();
     user::delete(&mut client, user_id);
 }
+
+#[test]
+fn undelete() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let created_value = create_thread(&mut client, &user_token);
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"])
+        .assert_no_content();
+    client
+        .with_base(BASE)
+        .get(&created_value["id"])
+        .assert_not_found();
+
+    // test
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            format!("{}/undelete", created_value["id"]),
+            json!({}),
+        )
+        .assert_ok();
+    let body = client
+        .with_base(BASE)
+        .get(&created_value["id"])
+        .assert_ok()
+        .get_body_object();
+
+    // Deleting and undeleting the thread each increment its version.
+    let mut expected = created_value.clone();
+    expected["version"] = json!(3);
+    assert_eq!(body, expected);
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn delete_cascade() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let created_value = create_thread(&mut client, &user_token);
+    let section_id = client
+        .with_base("/v1/section")
+        .post(
+            Some(&user_token),
+            json!({
+                "in_thread_id": created_value["id"],
+                "name": guid(),
+                "content": guid(),
+            }),
+        )
+        .assert_created()
+        .get_body_object()["id"]
+        .clone();
+    let event_id = client
+        .with_base("/v1/event")
+        .post(
+            Some(&user_token),
+            json!({
+                "in_thread_id": created_value["id"],
+                "cols": [],
+            }),
+        )
+        .assert_created()
+        .get_body_object()["id"]
+        .clone();
+
+    // test
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"])
+        .assert_no_content();
+    client
+        .with_base("/v1/section")
+        .get(&section_id)
+        .assert_not_found();
+    client
+        .with_base("/v1/event")
+        .get(&event_id)
+        .assert_not_found();
+
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            format!("{}/undelete", created_value["id"]),
+            json!({}),
+        )
+        .assert_ok();
+    let body = client
+        .with_base("/v1/section")
+        .get(&section_id)
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["deleted_at_utc"], json!(null));
+    let body = client
+        .with_base("/v1/event")
+        .get(&event_id)
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["deleted_at_utc"], json!(null));
+    let body = client
+        .with_base(BASE)
+        .get(&created_value["id"])
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["sections_id"], json!([section_id]));
+    assert_eq!(body["events_id"], json!([event_id]));
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn get_sync() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let created_value = create_thread(&mut client, &user_token);
+
+    // test
+    let body = client
+        .with_base(BASE)
+        .get(format!("{}/sync", created_value["id"]))
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(
+        body,
+        json!({
+            "thread_id": created_value["id"],
+            "post_id": null,
+            "synced_at_utc": null,
+            "is_up_to_date": false,
+            "overflow": null,
+            "is_pending": false,
+        })
+    );
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&user_token), &created_value["id"]);
+    user::delete(&mut client, user_id);
+}
@@ -1,4 +1,12 @@
-use crate::{guid, tests::helpers::*};
+use crate::{
+    controller::{UpdateUserRoles, User},
+    guid,
+    schema::user::dsl as user_table,
+    server,
+    tests::helpers::*,
+    DataDB,
+};
+use diesel::{sql_query, Connection, ExpressionMethods, QueryDsl, RunQueryDsl};
 use serde_json::{json, Value as Json};
 
 const BASE: &str = "/v1/user";
@@ -51,6 +59,7 @@ fn get_one() {
             "reddit_username": created_value["reddit_username"],
             "lang": created_value["lang"],
             "is_global_admin": created_value["is_global_admin"],
+            "needs_reauth": false,
         })
     );
 
@@ -121,6 +130,139 @@ fn update() {
     user::delete(&mut client, created_value["id"].as_i64().unwrap() as i32)
 }
 
+#[test]
+#[should_panic]
+fn refresh_other_user() {
+    let mut client = Client::new();
+    let created_value = create_user(&mut client);
+    let (_, other_token) = user::create(&mut client);
+
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&other_token),
+            format!("{}/refresh", created_value["id"]),
+            json!({}),
+        )
+        .assert_ok();
+}
+
+#[test]
+fn get_roles() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let (user_id, _) = user::create(&mut client);
+
+    // test
+    let body = client
+        .with_base(BASE)
+        .get_authenticated(&admin_token, format!("{}/roles", user_id))
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["user_id"], user_id);
+    assert_eq!(body["is_global_admin"], false);
+    assert_eq!(body["subreddit_roles"], json!([]));
+
+    // teardown
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+fn update_roles() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let (user_id, user_token) = user::create(&mut client);
+
+    // test
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&admin_token),
+            format!("{}/roles", user_id),
+            json!({ "is_global_admin": true }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["is_global_admin"], true);
+
+    // There's another global admin, so the user is able to demote themselves.
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            format!("{}/roles", user_id),
+            json!({ "is_global_admin": false }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["is_global_admin"], false);
+
+    // teardown
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+fn update_roles_not_admin() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+
+    // test
+    client
+        .with_base(BASE)
+        .patch(
+            Some(&user_token),
+            format!("{}/roles", user_id),
+            json!({ "is_global_admin": true }),
+        )
+        .assert_unauthorized();
+
+    // teardown
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn update_roles_last_admin() {
+    let mut client = Client::new();
+    let conn = DataDB::get_one(&server()).unwrap();
+
+    // setup
+    let (admin_id, _) = user::create_admin(&mut client);
+
+    // test
+    // Other tests create global admins concurrently,
+    // so everyone else is demoted in a transaction that is never committed.
+    conn.test_transaction::<_, diesel::result::Error, _>(|| {
+        sql_query(r#"LOCK TABLE "user" IN EXCLUSIVE MODE"#).execute(&*conn)?;
+        diesel::update(user_table::user.filter(user_table::id.ne(admin_id)))
+            .set(user_table::is_global_admin.eq(false))
+            .execute(&*conn)?;
+
+        let result = User::update_roles(
+            &conn,
+            admin_id,
+            &UpdateUserRoles {
+                is_global_admin:         Some(false),
+                spacex__is_slack_member: None,
+            },
+            Some(admin_id),
+        )?;
+        assert!(result.is_none());
+
+        Ok(())
+    });
+
+    // teardown
+    user::delete(&mut client, admin_id);
+}
+
 #[test]
 fn delete() {
     let mut client = Client::new();
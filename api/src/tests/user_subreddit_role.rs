@@ -0,0 +1,192 @@
+use crate::{
+    controller::{Role, UserSubredditRole},
+    guid,
+    server,
+    tests::helpers::*,
+    DataDB,
+};
+use serde_json::{json, Value as Json};
+
+const BASE: &str = "/v1/user_subreddit_role";
+
+fn create_role(client: &mut Client<'_>, token: &str, user_id: i32) -> Json {
+    client
+        .with_base(BASE)
+        .post(
+            Some(token),
+            json!({
+                "user_id": user_id,
+                "subreddit": "SpaceX",
+                "role": "host",
+            }),
+        )
+        .assert_created()
+        .get_body_object()
+}
+
+#[test]
+fn get_all() {
+    Client::new()
+        .with_base(BASE)
+        .get_all()
+        .assert_ok()
+        .get_body_array();
+}
+
+#[test]
+fn create() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let (user_id, _) = user::create(&mut client);
+
+    // test
+    let mut body = create_role(&mut client, &admin_token, user_id);
+    assert!(body["id"].is_number(), r#"body["id"] is number"#);
+    let id = body["id"].take();
+
+    // Subreddits are stored in lowercase.
+    assert_eq!(
+        body,
+        json!({
+            "id": null,
+            "user_id": user_id,
+            "subreddit": "spacex",
+            "role": "host",
+            "is_from_reddit": false,
+        })
+    );
+
+    // teardown
+    client.with_base(BASE).delete(Some(&admin_token), id);
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+#[should_panic]
+fn create_not_admin() {
+    let mut client = Client::new();
+    let (user_id, user_token) = user::create(&mut client);
+
+    create_role(&mut client, &user_token, user_id);
+}
+
+#[test]
+#[should_panic]
+fn create_unknown_role() {
+    let mut client = Client::new();
+    let (_, admin_token) = user::create_admin(&mut client);
+    let (user_id, _) = user::create(&mut client);
+
+    client
+        .with_base(BASE)
+        .post(
+            Some(&admin_token),
+            json!({
+                "user_id": user_id,
+                "subreddit": guid(),
+                "role": "admin",
+            }),
+        )
+        .assert_created();
+}
+
+#[test]
+fn update() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let (user_id, _) = user::create(&mut client);
+    let created_value = create_role(&mut client, &admin_token, user_id);
+
+    // test
+    let body = client
+        .with_base(BASE)
+        .patch(
+            Some(&admin_token),
+            &created_value["id"],
+            json!({ "role": "moderator" }),
+        )
+        .assert_ok()
+        .get_body_object();
+    assert_eq!(body["role"], "moderator");
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&admin_token), &created_value["id"]);
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+fn delete() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let (user_id, _) = user::create(&mut client);
+    let created_value = create_role(&mut client, &admin_token, user_id);
+
+    // test
+    client
+        .with_base(BASE)
+        .delete(Some(&admin_token), &created_value["id"])
+        .assert_no_content();
+
+    // teardown
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+fn set_subreddits_keeps_granted_roles() {
+    let mut client = Client::new();
+    let conn = DataDB::get_one(&server()).unwrap();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let (user_id, _) = user::create(&mut client);
+    let granted = client
+        .with_base(BASE)
+        .post(
+            Some(&admin_token),
+            json!({
+                "user_id": user_id,
+                "subreddit": "SpaceX",
+                "role": "moderator",
+            }),
+        )
+        .assert_created()
+        .get_body_object();
+
+    // test
+    let roles = UserSubredditRole::set_subreddits(
+        &conn,
+        user_id,
+        Role::Moderator,
+        &["SpaceXLounge".into()],
+    )
+    .unwrap();
+    let subreddits: Vec<_> = roles
+        .iter()
+        .map(|value| (value.subreddit.as_str(), value.is_from_reddit))
+        .collect();
+    assert_eq!(subreddits, vec![("spacex", false), ("spacexlounge", true)]);
+
+    // The synced role is removed once it is no longer held on Reddit,
+    // but the granted role is unaffected.
+    let roles = UserSubredditRole::set_subreddits(&conn, user_id, Role::Moderator, &[]).unwrap();
+    assert_eq!(roles.len(), 1);
+    assert_eq!(roles[0].id, granted["id"].as_i64().unwrap() as i32);
+
+    // teardown
+    client
+        .with_base(BASE)
+        .delete(Some(&admin_token), &granted["id"]);
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
@@ -0,0 +1,372 @@
+use crate::{guid, tests::helpers::*};
+use serde_json::json;
+
+#[test]
+fn ping() {
+    let socket = WebSocket::connect();
+
+    let reply = socket.send(json!({ "type": "ping" })).recv();
+    assert_eq!(reply, json!({ "type": "pong" }));
+}
+
+#[test]
+fn join_leave() {
+    let socket = WebSocket::connect();
+
+    // test
+    let reply = socket
+        .send(json!({ "type": "join", "rooms": ["thread_1", "section_2", "foo"] }))
+        .recv();
+    assert_eq!(
+        reply,
+        json!({
+            "type": "join",
+            "rooms": [
+                { "room": "thread_1", "ok": true },
+                { "room": "section_2", "ok": true },
+                { "room": "foo", "ok": false, "error": "unknown room name" },
+            ],
+        })
+    );
+
+    let reply = socket.send(json!({ "type": "list" })).recv();
+    assert_eq!(
+        reply,
+        json!({ "type": "list", "rooms": ["section_2", "thread_1"] })
+    );
+
+    let reply = socket
+        .send(json!({ "type": "leave", "rooms": ["thread_1", "event_3"] }))
+        .recv();
+    assert_eq!(
+        reply,
+        json!({
+            "type": "leave",
+            "rooms": [
+                { "room": "thread_1", "ok": true },
+                { "room": "event_3", "ok": false, "error": "not in room" },
+            ],
+        })
+    );
+
+    let reply = socket.send(json!({ "type": "list" })).recv();
+    assert_eq!(reply, json!({ "type": "list", "rooms": ["section_2"] }));
+}
+
+#[test]
+fn invalid_request() {
+    let socket = WebSocket::connect();
+
+    let reply = socket.send("not json").recv();
+    assert_eq!(reply["type"], "error");
+    assert!(reply["error"].is_string());
+
+    let reply = socket.send(json!({ "type": "foo" })).recv();
+    assert_eq!(reply["type"], "error");
+    assert!(reply["error"].is_string());
+}
+
+#[test]
+fn receive_updates() {
+    let mut client = Client::new();
+    let socket = WebSocket::connect();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let section_id = client
+        .with_base("/v1/section")
+        .post(Some(&user_token), json!({ "in_thread_id": thread_id }))
+        .assert_created()
+        .get_body_object()["id"]
+        .clone();
+    let room = format!("section_{}", section_id);
+    socket
+        .send(json!({ "type": "join", "rooms": [room] }))
+        .recv();
+
+    // test
+    let data = json!({ "name": guid() });
+    client
+        .with_base("/v1/section")
+        .patch(Some(&user_token), &section_id, &data)
+        .assert_ok();
+    let message = socket.recv();
+    assert_eq!(message["room"], room);
+    assert_eq!(message["action"], "update");
+    assert_eq!(message["data_type"], "section");
+    assert_eq!(message["data"]["id"], section_id);
+    assert_eq!(message["data"]["name"], data["name"]);
+
+    // Once the room has been left, no further updates are received.
+    socket
+        .send(json!({ "type": "leave", "rooms": [room] }))
+        .recv();
+    client
+        .with_base("/v1/section")
+        .patch(Some(&user_token), &section_id, json!({ "name": guid() }))
+        .assert_ok();
+    let reply = socket.send(json!({ "type": "ping" })).recv();
+    assert_eq!(reply, json!({ "type": "pong" }));
+
+    // teardown
+    client
+        .with_base("/v1/section")
+        .delete(Some(&user_token), &section_id);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn private_room() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let (other_id, _) = user::create(&mut client);
+    let socket = WebSocket::connect_authenticated(&user_token);
+
+    // test
+    let own_room = format!("user_{}", user_id);
+    let other_room = format!("user_{}", other_id);
+    let reply = socket
+        .send(json!({ "type": "join", "rooms": [own_room, other_room, "user"] }))
+        .recv();
+    assert_eq!(
+        reply,
+        json!({
+            "type": "join",
+            "rooms": [
+                { "room": own_room, "ok": true },
+                { "room": other_room, "ok": false, "error": "unauthorized" },
+                { "room": "user", "ok": false, "error": "unauthorized" },
+            ],
+        })
+    );
+
+    // teardown
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, other_id);
+}
+
+#[test]
+fn demoted_admin() {
+    let mut client = Client::new();
+
+    // setup
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let (other_id, other_token) = user::create_admin(&mut client);
+    let socket = WebSocket::connect_authenticated(&admin_token);
+    let reply = socket
+        .send(json!({ "type": "join", "rooms": ["user"] }))
+        .recv();
+    assert_eq!(reply["rooms"][0]["ok"], true);
+
+    // test
+    client
+        .with_base("/v1/user")
+        .patch(
+            Some(&other_token),
+            format!("{}/roles", admin_id),
+            json!({ "is_global_admin": false }),
+        )
+        .assert_ok();
+
+    // Changes to other users may have been received before the connection was removed.
+    socket.send(json!({ "type": "list" }));
+    let reply = loop {
+        let message = socket.recv();
+        if message["type"] == "list" {
+            break message;
+        }
+    };
+    assert_eq!(reply, json!({ "type": "list", "rooms": [] }));
+
+    let reply = socket
+        .send(json!({ "type": "join", "rooms": ["user"] }))
+        .recv();
+    assert_eq!(reply["rooms"][0]["error"], "unauthorized");
+
+    // teardown
+    user::delete(&mut client, admin_id);
+    user::delete(&mut client, other_id);
+}
+
+#[test]
+fn auth_request() {
+    let mut client = Client::new();
+    let socket = WebSocket::connect();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let room = format!("user_{}", user_id);
+
+    // test
+    let reply = socket
+        .send(json!({ "type": "join", "rooms": [room] }))
+        .recv();
+    assert_eq!(reply["rooms"][0]["error"], "unauthorized");
+
+    let reply = socket
+        .send(json!({ "type": "auth", "token": guid() }))
+        .recv();
+    assert_eq!(
+        reply,
+        json!({ "type": "auth", "ok": false, "error": "invalid token" })
+    );
+
+    let reply = socket
+        .send(json!({ "type": "auth", "token": user_token }))
+        .recv();
+    assert_eq!(reply, json!({ "type": "auth", "ok": true }));
+
+    let reply = socket
+        .send(json!({ "type": "join", "rooms": [room] }))
+        .recv();
+    assert_eq!(reply["rooms"][0]["ok"], true);
+
+    let reply = socket
+        .send(json!({ "type": "auth", "token": user_token }))
+        .recv();
+    assert_eq!(
+        reply,
+        json!({ "type": "auth", "ok": false, "error": "already authenticated" })
+    );
+
+    // teardown
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+#[should_panic]
+fn connect_invalid_token() {
+    WebSocket::connect_authenticated(&guid());
+}
+
+#[test]
+fn lock_lost() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let (admin_id, admin_token) = user::create_admin(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let section_id = client
+        .with_base("/v1/section")
+        .post(Some(&user_token), json!({ "in_thread_id": thread_id }))
+        .assert_created()
+        .get_body_object()["id"]
+        .clone();
+    client
+        .with_base("/v1/section")
+        .patch(
+            Some(&user_token),
+            &section_id,
+            json!({ "lock_held_by_user_id": user_id }),
+        )
+        .assert_ok();
+    let socket = WebSocket::connect_authenticated(&user_token);
+    let room = format!("user_{}", user_id);
+    socket
+        .send(json!({ "type": "join", "rooms": [room] }))
+        .recv();
+
+    // test
+    client
+        .with_base("/v1/section")
+        .patch(
+            Some(&admin_token),
+            &section_id,
+            json!({ "lock_held_by_user_id": null }),
+        )
+        .assert_ok();
+    let message = socket.recv();
+    assert_eq!(message["room"], room);
+    assert_eq!(message["action"], "lock_lost");
+    assert_eq!(message["data_type"], "section");
+    assert_eq!(message["data"]["id"], section_id);
+    assert_eq!(message["data"]["lock_held_by_user_id"], json!(null));
+
+    // teardown
+    client
+        .with_base("/v1/section")
+        .delete(Some(&user_token), &section_id);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+    user::delete(&mut client, admin_id);
+}
+
+#[test]
+fn replay() {
+    let mut client = Client::new();
+
+    // setup
+    let (user_id, user_token) = user::create(&mut client);
+    let thread_id = thread::create(&mut client, &user_token);
+    let section_id = client
+        .with_base("/v1/section")
+        .post(Some(&user_token), json!({ "in_thread_id": thread_id }))
+        .assert_created()
+        .get_body_object()["id"]
+        .clone();
+    let room = format!("section_{}", section_id);
+
+    let socket = WebSocket::connect();
+    socket
+        .send(json!({ "type": "join", "rooms": [room] }))
+        .recv();
+    client
+        .with_base("/v1/section")
+        .patch(Some(&user_token), &section_id, json!({ "name": guid() }))
+        .assert_ok();
+    let seq = socket.recv()["seq"].as_u64().unwrap();
+    drop(socket);
+
+    // test
+    let names = [guid(), guid()];
+    for name in names.iter() {
+        client
+            .with_base("/v1/section")
+            .patch(Some(&user_token), &section_id, json!({ "name": name }))
+            .assert_ok();
+    }
+
+    // Missed messages are sent before the reply.
+    let socket = WebSocket::connect();
+    socket.send(json!({ "type": "join", "rooms": [{ "room": room, "since": seq }] }));
+    let mut last_seq = seq;
+    for name in names.iter() {
+        let message = socket.recv();
+        assert_eq!(message["room"], room);
+        assert_eq!(message["data"]["name"], *name);
+        assert!(message["seq"].as_u64().unwrap() > last_seq);
+        last_seq = message["seq"].as_u64().unwrap();
+    }
+    assert_eq!(
+        socket.recv(),
+        json!({ "type": "join", "rooms": [{ "room": room, "ok": true }] })
+    );
+
+    // teardown
+    client
+        .with_base("/v1/section")
+        .delete(Some(&user_token), &section_id);
+    thread::delete(&mut client, &user_token, thread_id);
+    user::delete(&mut client, user_id);
+}
+
+#[test]
+fn replay_resync() {
+    let socket = WebSocket::connect();
+
+    let reply = socket
+        .send(json!({ "type": "join", "rooms": [{ "room": "thread_create", "since": 0 }] }))
+        .recv();
+    assert_eq!(
+        reply,
+        json!({
+            "type": "join",
+            "rooms": [{ "room": "thread_create", "ok": true, "resync": true }],
+        })
+    );
+}
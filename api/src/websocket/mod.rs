@@ -1,14 +1,37 @@
+use crate::{
+    controller::{Claim, User},
+    DataDB,
+};
 use hashbrown::{HashMap, HashSet};
 use lazy_static::lazy_static;
-use parking_lot::RwLock;
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+use lru_cache::LruCache;
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::VecDeque,
+    net::ToSocketAddrs,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use ws::{
+    CloseCode,
+    Factory,
+    Handler,
+    Handshake,
+    Message as WsMessage,
+    Request as WsRequest,
+    Response as WsResponse,
+    Sender,
+    WebSocket,
 };
-use ws::{CloseCode, Handler, Handshake, Message as WsMessage, Sender};
 
 mod structs;
+mod subscription;
 pub use structs::*;
+pub use subscription::{Event, Subscription};
 
 lazy_static! {
     // We're using `Arc` and not `Weak`,
@@ -16,23 +39,145 @@ lazy_static! {
     // As such, we have to manually drop the reference
     // in the `on_close` method to prevent a memory leak.
     static ref ROOMS: RwLock<HashMap<Room, HashSet<Arc<Sender>>>> = RwLock::new(HashMap::new());
+
+    /// Authenticated connections, keyed by the ID of their user.
+    static ref USERS: RwLock<HashMap<i32, HashSet<Arc<Sender>>>> = RwLock::new(HashMap::new());
+
+    /// Messages recently sent to each room.
+    /// Any sends or joins must hold this lock throughout,
+    /// ensuring that clients receive every message exactly once and in order.
+    static ref HISTORY: Mutex<Histories> = Mutex::new(Histories::new());
 }
 
 pub static CONNECTED_CLIENTS: AtomicUsize = AtomicUsize::new(0);
 
+/// How many rooms to retain messages for, evicting the least recently active.
+const HISTORY_ROOMS: usize = 500;
+/// How many messages to retain for each room.
+const HISTORY_SIZE: usize = 100;
+
 #[cfg(debug_assertions)]
 const IP: &str = "127.0.0.1";
 #[cfg(not(debug_assertions))]
 const IP: &str = "0.0.0.0";
 const PORT: u16 = 3001;
 
-#[derive(Debug)]
+/// Messages recently sent to a single room.
+struct History {
+    /// The sequence number immediately preceding the oldest message retained.
+    /// Clients that last received an earlier message are unable to catch up.
+    floor:    u64,
+    messages: VecDeque<(u64, String)>,
+}
+
+/// Messages recently sent to all rooms,
+/// along with the sequence number to assign to the next message.
+struct Histories {
+    next_seq: u64,
+    rooms:    LruCache<Room, History>,
+}
+
+impl Histories {
+    /// Sequence numbers start from the current time in milliseconds,
+    /// so that they continue to increase after the server is restarted.
+    #[inline]
+    fn new() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        Histories {
+            next_seq: now,
+            rooms:    LruCache::new(HISTORY_ROOMS),
+        }
+    }
+
+    /// Assign the next sequence number to a message sent to the room,
+    /// retaining the message so it can be replayed.
+    #[inline]
+    fn record(&mut self, room: Room, message: impl FnOnce(u64) -> String) -> (u64, &str) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+
+        if !self.rooms.contains_key(&room) {
+            self.rooms.insert(
+                room,
+                History {
+                    floor:    seq - 1,
+                    messages: VecDeque::with_capacity(HISTORY_SIZE),
+                },
+            );
+        }
+        let history = self.rooms.get_mut(&room).unwrap();
+
+        if history.messages.len() == HISTORY_SIZE {
+            if let Some((discarded, _)) = history.messages.pop_front() {
+                history.floor = discarded;
+            }
+        }
+        history.messages.push_back((seq, message(seq)));
+
+        (seq, &history.messages.back().unwrap().1)
+    }
+
+    /// Messages sent to the room after the sequence number `since`,
+    /// or `None` if any of them are no longer retained.
+    #[inline]
+    fn since(&mut self, room: Room, since: u64) -> Option<Vec<(u64, &str)>> {
+        let latest = self.next_seq - 1;
+
+        match self.rooms.get_mut(&room) {
+            Some(history) if since >= history.floor => Some(
+                history
+                    .messages
+                    .iter()
+                    .filter(|&&(seq, _)| seq > since)
+                    .map(|(seq, message)| (*seq, message.as_str()))
+                    .collect(),
+            ),
+            // Nothing has been sent to any room since.
+            None if since >= latest => Some(vec![]),
+            _ => None,
+        }
+    }
+}
+
+/// A single connection.
+///
+/// All connections are handled on the same thread,
+/// so they share a single database connection.
 struct Socket {
-    out:   Arc<Sender>,
-    rooms: HashSet<Room>,
+    out:     Arc<Sender>,
+    rooms:   HashSet<Room>,
+    conn:    Rc<DataDB>,
+    /// The user is read again whenever it's needed,
+    /// so that any change to their roles takes effect.
+    user_id: Option<i32>,
 }
 
 impl Handler for Socket {
+    /// Authenticate the connection if a `token` is present in the query string,
+    /// as browsers are unable to set headers on WebSocket connections.
+    /// Connections with an invalid token are rejected outright.
+    #[inline]
+    fn on_request(&mut self, request: &WsRequest) -> ws::Result<WsResponse> {
+        let token = request
+            .resource()
+            .splitn(2, '?')
+            .nth(1)
+            .and_then(|query| query.split('&').find(|param| param.starts_with("token=")))
+            .map(|param| &param["token=".len()..]);
+
+        if let Some(token) = token {
+            if self.authenticate(token).is_err() {
+                return Ok(WsResponse::new(401, "Unauthorized", vec![]));
+            }
+        }
+
+        WsResponse::from_request(request)
+    }
+
     #[inline(always)]
     fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
         CONNECTED_CLIENTS.fetch_add(1, Ordering::Relaxed);
@@ -43,29 +188,62 @@ impl Handler for Socket {
     fn on_message(&mut self, message: WsMessage) -> ws::Result<()> {
         let message = match message {
             WsMessage::Text(s) => s,
-            _ => return Ok(()),
+            _ => {
+                return self.reply(&Reply::Error {
+                    error: "expected a text message".into(),
+                })
+            }
         };
 
-        let mut rooms = ROOMS.write();
+        self.prune_rooms();
 
-        for room in match serde_json::from_str(&message) {
-            Ok(JoinRequest { join }) => join,
-            _ => return Ok(()),
+        // Clients predating `Request` don't expect a reply.
+        if let Ok(JoinRequest { join }) = serde_json::from_str(&message) {
+            for room in join.iter() {
+                let _ = self.join(room, None);
+            }
+            return Ok(());
         }
-        .into_iter()
-        .filter_map(|s| s.parse().ok())
-        {
-            // Store the connection itself in the global room.
-            rooms
-                .entry(room)
-                .or_insert(HashSet::new())
-                .insert(Arc::clone(&self.out));
 
-            // Store the connection's rooms on the instance.
-            self.rooms.insert(room);
-        }
+        let reply = match serde_json::from_str(&message) {
+            Ok(Request::Join { rooms }) => Reply::Join {
+                rooms: rooms
+                    .into_iter()
+                    .map(|room| {
+                        let (room, since) = room.into_parts();
+                        let result = self.join(&room, since);
+                        RoomResult::new(room, result)
+                    })
+                    .collect(),
+            },
+            Ok(Request::Leave { rooms }) => Reply::Leave {
+                rooms: rooms
+                    .into_iter()
+                    .map(|room| {
+                        let result = self.leave(&room).map(|()| false);
+                        RoomResult::new(room, result)
+                    })
+                    .collect(),
+            },
+            Ok(Request::Auth { token }) => {
+                let result = self.authenticate(&token);
+                Reply::Auth {
+                    ok:    result.is_ok(),
+                    error: result.err(),
+                }
+            }
+            Ok(Request::Ping) => Reply::Pong,
+            Ok(Request::List) => {
+                let mut rooms: Vec<_> = self.rooms.iter().map(ToString::to_string).collect();
+                rooms.sort();
+                Reply::List { rooms }
+            }
+            Err(err) => Reply::Error {
+                error: err.to_string(),
+            },
+        };
 
-        Ok(())
+        self.reply(&reply)
     }
 
     #[inline]
@@ -78,7 +256,18 @@ impl Handler for Socket {
             // These should be the final references to the values,
             // so doing this should call `Drop` and free up the memory.
             for room in self.rooms.iter() {
-                rooms.get_mut(room).unwrap().remove(&self.out);
+                if let Some(clients) = rooms.get_mut(room) {
+                    clients.remove(&self.out);
+                }
+            }
+        }
+
+        if let Some(user_id) = self.user_id {
+            let mut users = USERS.write();
+            let connections = users.get_mut(&user_id).unwrap();
+            connections.remove(&self.out);
+            if connections.is_empty() {
+                users.remove(&user_id);
             }
         }
 
@@ -86,12 +275,164 @@ impl Handler for Socket {
     }
 }
 
+impl Socket {
+    /// Authenticate the connection with a JWT, as would be used in the `Authorization` header.
+    /// A connection may only be authenticated once.
+    #[inline]
+    fn authenticate(&mut self, token: &str) -> Result<(), &'static str> {
+        if self.user_id.is_some() {
+            return Err("already authenticated");
+        }
+
+        let user_id = Claim::get_user_id(token).map_err(|_| "invalid token")?;
+        User::find_id(&self.conn, user_id).map_err(|_| "unable to find user")?;
+        self.user_id = Some(user_id);
+
+        USERS
+            .write()
+            .entry(user_id)
+            .or_insert_with(HashSet::new)
+            .insert(Arc::clone(&self.out));
+
+        Ok(())
+    }
+
+    /// The user the connection is authenticated as, if any.
+    #[inline]
+    fn user(&self) -> Result<Option<User>, &'static str> {
+        match self.user_id {
+            Some(user_id) => User::find_id(&self.conn, user_id)
+                .map(Some)
+                .map_err(|_| "unable to find user"),
+            None => Ok(None),
+        }
+    }
+
+    /// Forget any rooms the connection has been removed from by `evict`.
+    #[inline]
+    fn prune_rooms(&mut self) {
+        if self.rooms.is_empty() {
+            return;
+        }
+
+        let out = &self.out;
+        let rooms = ROOMS.read();
+        self.rooms.retain(|room| {
+            rooms
+                .get(room)
+                .map_or(false, |clients| clients.contains(out))
+        });
+    }
+
+    /// Join the room, storing the connection in the global room
+    /// and the room on the instance.
+    /// Returns an error if the connection is not authorized to join the room.
+    ///
+    /// If `since` is provided, any messages sent to the room after that sequence number
+    /// are sent to the connection immediately.
+    /// Returns `true` if this isn't possible and the client must resync.
+    #[inline]
+    fn join(&mut self, room: &str, since: Option<u64>) -> Result<bool, &'static str> {
+        let room: Room = room.parse()?;
+
+        // Holding the lock while checking the user ensures that
+        // a concurrent `evict` either sees the connection in the room or
+        // has already updated the user that is read here.
+        let mut history = HISTORY.lock();
+        if !room.may_join(self.user()?.as_ref()) {
+            return Err("unauthorized");
+        }
+
+        ROOMS
+            .write()
+            .entry(room)
+            .or_insert_with(HashSet::new)
+            .insert(Arc::clone(&self.out));
+        self.rooms.insert(room);
+
+        let since = match since {
+            Some(since) => since,
+            None => return Ok(false),
+        };
+        match history.since(room, since) {
+            Some(messages) => {
+                for (_, message) in messages {
+                    let _ = self.out.send(message);
+                }
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Leave a room that has previously been joined.
+    #[inline]
+    fn leave(&mut self, room: &str) -> Result<(), &'static str> {
+        let room = room.parse()?;
+        if !self.rooms.remove(&room) {
+            return Err("not in room");
+        }
+
+        let mut rooms = ROOMS.write();
+        if let Some(clients) = rooms.get_mut(&room) {
+            clients.remove(&self.out);
+            if clients.is_empty() {
+                rooms.remove(&room);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send a reply to this connection alone.
+    #[inline]
+    fn reply(&self, reply: &Reply) -> ws::Result<()> {
+        self.out.send(serde_json::to_string(reply).unwrap())
+    }
+}
+
+/// Remove the user's connections from any rooms they are no longer permitted to join,
+/// such as the `user` room after they have been demoted.
+///
+/// This must be called after the user has been written to cache.
+#[inline]
+pub fn evict(user: &User) {
+    let _history = HISTORY.lock();
+
+    let users = USERS.read();
+    let connections = match users.get(&user.id) {
+        Some(connections) => connections,
+        None => return,
+    };
+
+    ROOMS.write().retain(|room, clients| {
+        if !room.may_join(Some(user)) {
+            for connection in connections.iter() {
+                clients.remove(connection);
+            }
+        }
+        !clients.is_empty()
+    });
+}
+
+/// Bind the server to the provided address,
+/// without accepting any connections until it is run.
+#[inline]
+pub fn bind(addr: impl ToSocketAddrs, conn: DataDB) -> ws::Result<WebSocket<impl Factory>> {
+    let conn = Rc::new(conn);
+
+    WebSocket::new(move |out| Socket {
+        out:     Arc::new(out),
+        rooms:   HashSet::new(),
+        conn:    Rc::clone(&conn),
+        user_id: None,
+    })?
+    .bind(addr)
+}
+
 #[inline]
-pub fn spawn() {
-    let addr = format!("{}:{}", IP, PORT);
-    ws::listen(addr, |out| Socket {
-        out:   Arc::new(out),
-        rooms: HashSet::new(),
-    })
-    .unwrap();
+pub fn spawn(conn: DataDB) {
+    bind(format!("{}:{}", IP, PORT), conn)
+        .and_then(WebSocket::run)
+        .unwrap();
 }
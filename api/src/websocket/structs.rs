@@ -1,3 +1,4 @@
+use crate::controller::User;
 #[cfg(feature = "telemetry")]
 use crate::telemetry::log_sent_message;
 use derive_more::{Constructor, Display};
@@ -7,20 +8,122 @@ use std::time::Instant;
 
 /// A request from a client to join certain rooms.
 /// Each element should be able to be parsed with `Room::from_str`.
+///
+/// This predates `Request`, and is only accepted for compatibility.
+/// No reply is sent.
 #[derive(Deserialize, Debug)]
 pub struct JoinRequest {
     pub join: Vec<String>,
 }
 
+/// A request from a client, discriminated by its `type` field.
+///
+/// Every request is acknowledged with a `Reply`.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Request {
+    /// Join the rooms, each of which should be able to be parsed with `Room::from_str`.
+    Join { rooms: Vec<JoinRoom> },
+    /// Leave the rooms, each of which should be able to be parsed with `Room::from_str`.
+    Leave { rooms: Vec<String> },
+    /// Check that the connection is still alive.
+    Ping,
+    /// List the rooms the client is currently in.
+    List,
+    /// Authenticate with a JWT, as would be used in the `Authorization` header.
+    Auth { token: String },
+}
+
+/// A room to join, optionally with the sequence number of the last message received from it.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum JoinRoom {
+    Room(String),
+    Since { room: String, since: u64 },
+}
+
+impl JoinRoom {
+    #[inline]
+    pub fn into_parts(self) -> (String, Option<u64>) {
+        match self {
+            JoinRoom::Room(room) => (room, None),
+            JoinRoom::Since { room, since } => (room, Some(since)),
+        }
+    }
+}
+
+/// The outcome of joining or leaving a single room.
+#[derive(Serialize, Debug)]
+pub struct RoomResult {
+    pub room:   String,
+    pub ok:     bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error:  Option<&'static str>,
+    /// Messages that were missed are no longer retained,
+    /// so the client must fetch the current state.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub resync: bool,
+}
+
+impl RoomResult {
+    /// `result` contains whether the client must resync.
+    #[inline]
+    pub fn new(room: String, result: Result<bool, &'static str>) -> Self {
+        RoomResult {
+            room,
+            ok: result.is_ok(),
+            error: result.err(),
+            resync: result.unwrap_or(false),
+        }
+    }
+}
+
+/// A reply to a `Request`, sent only to the client that made it.
+///
+/// Unlike a `Message`, a reply always has a `type` field.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Reply {
+    Join {
+        rooms: Vec<RoomResult>,
+    },
+    Leave {
+        rooms: Vec<RoomResult>,
+    },
+    Pong,
+    List {
+        rooms: Vec<String>,
+    },
+    Auth {
+        ok:    bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<&'static str>,
+    },
+    /// The request could not be understood.
+    Error {
+        error: String,
+    },
+}
+
 /// Room to send a `Message` to.
+///
+/// Changes to a section or event are sent to both its own room and that of its thread,
+/// allowing clients to subscribe to as much or as little as they need.
 #[derive(Debug, Hash, PartialEq, Eq, Display, Clone, Copy)]
 pub enum Room {
     #[display(fmt = "user")]
     User,
+    /// Notifications intended for a single user, such as their lock being taken.
+    #[display(fmt = "user_{}", _0)]
+    Private(i32),
     #[display(fmt = "thread_create")]
     ThreadCreate,
     #[display(fmt = "thread_{}", _0)]
     Thread(i32),
+    #[display(fmt = "section_{}", _0)]
+    Section(i32),
+    #[display(fmt = "event_{}", _0)]
+    Event(i32),
 }
 
 impl std::str::FromStr for Room {
@@ -30,16 +133,44 @@ impl std::str::FromStr for Room {
     fn from_str(string: &str) -> Result<Self, Self::Err> {
         match string {
             "user" => Ok(Room::User),
+            room if room.starts_with("user_") => match room["user_".len()..].parse() {
+                Ok(id) => Ok(Room::Private(id)),
+                Err(_) => Err("invalid user id"),
+            },
             "thread_create" => Ok(Room::ThreadCreate),
             room if room.starts_with("thread_") => match room["thread_".len()..].parse() {
                 Ok(id) => Ok(Room::Thread(id)),
                 Err(_) => Err("invalid thread id"),
             },
+            room if room.starts_with("section_") => match room["section_".len()..].parse() {
+                Ok(id) => Ok(Room::Section(id)),
+                Err(_) => Err("invalid section id"),
+            },
+            room if room.starts_with("event_") => match room["event_".len()..].parse() {
+                Ok(id) => Ok(Room::Event(id)),
+                Err(_) => Err("invalid event id"),
+            },
             _ => Err("unknown room name"),
         }
     }
 }
 
+impl Room {
+    /// May the provided user (or an unauthenticated client) join the room?
+    ///
+    /// Changes to users are only sent to global admins,
+    /// and private rooms only to the user in question.
+    /// All other rooms mirror publicly available data, so anyone may join them.
+    #[inline]
+    pub fn may_join(self, user: Option<&User>) -> bool {
+        match self {
+            Room::User => user.map_or(false, |user| user.is_global_admin),
+            Room::Private(user_id) => user.map_or(false, |user| user.id == user_id),
+            _ => true,
+        }
+    }
+}
+
 /// What action is the `data` field representing in a `Message`?
 #[derive(Debug, Display)]
 pub enum Action {
@@ -49,11 +180,16 @@ pub enum Action {
     Update,
     #[display(fmt = "delete")]
     Delete,
+    /// Sent to the previous holder of a lock when it is taken, broken, or expires.
+    #[display(fmt = "lock_lost")]
+    LockLost,
 }
 
 /// What type is the `data` field in a `Message`?
 #[derive(Debug, Display)]
 pub enum DataType {
+    #[display(fmt = "comment")]
+    Comment,
     #[display(fmt = "event")]
     Event,
     #[display(fmt = "section")]
@@ -62,6 +198,8 @@ pub enum DataType {
     Thread,
     #[display(fmt = "user")]
     User,
+    #[display(fmt = "user_subreddit_role")]
+    UserSubredditRole,
 }
 
 /// A message that can be emitted to the various WebSocket clients.
@@ -76,22 +214,29 @@ pub struct Message<'a, T: Serialize> {
 }
 
 impl<T: Serialize> Message<'_, T> {
+    /// Send the message to all clients and subscriptions in the room,
+    /// retaining it for any clients that later rejoin.
     #[inline]
     pub fn send(&self) -> ws::Result<()> {
+        let mut history = super::HISTORY.lock();
+        let (seq, message) = history.record(self.room, |seq| {
+            json!({
+                "room": self.room.to_string(),
+                "seq": seq,
+                "action": self.action.to_string(),
+                "data_type": self.data_type.to_string(),
+                "data": self.data,
+            })
+            .to_string()
+        });
+        super::subscription::publish(self.room, seq, message);
+
         let rooms = super::ROOMS.read();
         let clients = match rooms.get(&self.room) {
             Some(v) => v,
             None => return Ok(()),
         };
 
-        let message = &*json!({
-            "room": self.room.to_string(),
-            "action": self.action.to_string(),
-            "data_type": self.data_type.to_string(),
-            "data": self.data,
-        })
-        .to_string();
-
         #[cfg(feature = "telemetry")]
         {
             let send_start = Instant::now();
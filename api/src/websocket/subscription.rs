@@ -0,0 +1,132 @@
+use super::{Room, HISTORY};
+use hashbrown::{HashMap, HashSet};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    time::Duration,
+};
+
+lazy_static! {
+    /// Subscriptions to each room, keyed by their ID.
+    /// A `Mutex` is used rather than a `RwLock`, as `mpsc::Sender` is not `Sync`.
+    static ref SUBSCRIPTIONS: Mutex<HashMap<Room, HashMap<usize, Sender<Event>>>> =
+        Mutex::new(HashMap::new());
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Something received by a `Subscription`.
+#[derive(Debug)]
+pub enum Event {
+    /// A message sent to a room, along with its sequence number.
+    Message(u64, String),
+    /// Messages sent to the room were missed and are no longer retained,
+    /// so the current state must be fetched.
+    Resync(Room),
+}
+
+/// A subscription to rooms from outside the WebSocket server,
+/// receiving the same messages as WebSocket clients in those rooms.
+///
+/// The subscription ends when dropped.
+#[derive(Debug)]
+pub struct Subscription {
+    id:       usize,
+    rooms:    HashSet<Room>,
+    receiver: Receiver<Event>,
+}
+
+impl Subscription {
+    /// Subscribe to the rooms.
+    /// Authority to do so is _not_ verified here.
+    ///
+    /// If `since` is provided, any messages sent to the rooms after that sequence number
+    /// are received first, in the order they were sent.
+    #[inline]
+    pub fn new(rooms: HashSet<Room>, since: Option<u64>) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+
+        // As when joining a room over WebSockets,
+        // holding the history ensures no message is missed or received twice.
+        let mut history = HISTORY.lock();
+
+        if let Some(since) = since {
+            let mut missed = vec![];
+            for &room in rooms.iter() {
+                match history.since(room, since) {
+                    Some(messages) => missed.extend(
+                        messages
+                            .into_iter()
+                            .map(|(seq, message)| (seq, message.to_owned())),
+                    ),
+                    None => {
+                        let _ = sender.send(Event::Resync(room));
+                    }
+                }
+            }
+
+            missed.sort_by_key(|&(seq, _)| seq);
+            for (seq, message) in missed {
+                let _ = sender.send(Event::Message(seq, message));
+            }
+        }
+
+        let mut subscriptions = SUBSCRIPTIONS.lock();
+        for &room in rooms.iter() {
+            subscriptions
+                .entry(room)
+                .or_insert_with(HashMap::new)
+                .insert(id, sender.clone());
+        }
+
+        Subscription {
+            id,
+            rooms,
+            receiver,
+        }
+    }
+
+    /// Receive the next event if one is waiting.
+    #[inline]
+    pub fn try_recv(&self) -> Option<Event> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Wait for the next event, returning `None` if none arrives within the timeout.
+    #[inline]
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Event> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for Subscription {
+    #[inline]
+    fn drop(&mut self) {
+        let mut subscriptions = SUBSCRIPTIONS.lock();
+
+        for room in self.rooms.iter() {
+            if let Some(subscribers) = subscriptions.get_mut(room) {
+                subscribers.remove(&self.id);
+                if subscribers.is_empty() {
+                    subscriptions.remove(room);
+                }
+            }
+        }
+    }
+}
+
+/// Send a message to all subscriptions in the room.
+/// The caller must hold the history, so messages are received in order.
+#[inline]
+pub(super) fn publish(room: Room, seq: u64, message: &str) {
+    if let Some(subscribers) = SUBSCRIPTIONS.lock().get(&room) {
+        for sender in subscribers.values() {
+            let _ = sender.send(Event::Message(seq, message.to_owned()));
+        }
+    }
+}
@@ -0,0 +1 @@
+DROP TABLE reddit_sync;
@@ -0,0 +1,7 @@
+CREATE TABLE reddit_sync (
+  thread_id INTEGER PRIMARY KEY NOT NULL
+    REFERENCES thread
+    ON DELETE CASCADE,
+  markdown TEXT NOT NULL,
+  synced_at_utc BIGINT NOT NULL
+);
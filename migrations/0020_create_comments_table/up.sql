@@ -0,0 +1,11 @@
+CREATE TABLE comment (
+  id SERIAL PRIMARY KEY NOT NULL,
+  in_thread_id INTEGER NOT NULL
+    REFERENCES thread
+    ON DELETE RESTRICT,
+  reddit_id VARCHAR(16),
+  sections_id INTEGER[] NOT NULL DEFAULT '{}',
+  is_stickied BOOLEAN NOT NULL DEFAULT false,
+  is_continuation BOOLEAN NOT NULL DEFAULT false,
+  markdown TEXT NOT NULL DEFAULT ''
+);
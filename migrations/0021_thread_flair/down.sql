@@ -0,0 +1,3 @@
+ALTER TABLE thread
+DROP COLUMN flair_id,
+DROP COLUMN flair_text;
@@ -0,0 +1,3 @@
+ALTER TABLE thread
+ADD COLUMN flair_id VARCHAR(36),
+ADD COLUMN flair_text VARCHAR(64);
@@ -0,0 +1,21 @@
+ALTER TABLE "user"
+ADD COLUMN spacex__is_host BOOLEAN NOT NULL DEFAULT false,
+ADD COLUMN spacex__is_mod BOOLEAN NOT NULL DEFAULT false;
+
+UPDATE "user"
+SET spacex__is_host = true
+WHERE id IN (
+  SELECT user_id
+  FROM user_subreddit_role
+  WHERE subreddit = 'spacex' AND role = 'host'
+);
+
+UPDATE "user"
+SET spacex__is_mod = true
+WHERE id IN (
+  SELECT user_id
+  FROM user_subreddit_role
+  WHERE subreddit = 'spacex' AND role = 'moderator'
+);
+
+DROP TABLE user_subreddit_role;
@@ -0,0 +1,24 @@
+CREATE TABLE user_subreddit_role (
+  id SERIAL PRIMARY KEY NOT NULL,
+  user_id INTEGER NOT NULL
+    REFERENCES "user"
+    ON DELETE CASCADE,
+  subreddit VARCHAR(21) NOT NULL,
+  role VARCHAR(16) NOT NULL
+    CHECK (role IN ('host', 'moderator')),
+  UNIQUE (user_id, subreddit, role)
+);
+
+INSERT INTO user_subreddit_role (user_id, subreddit, role)
+SELECT id, 'spacex', 'host'
+FROM "user"
+WHERE spacex__is_host;
+
+INSERT INTO user_subreddit_role (user_id, subreddit, role)
+SELECT id, 'spacex', 'moderator'
+FROM "user"
+WHERE spacex__is_mod;
+
+ALTER TABLE "user"
+DROP COLUMN spacex__is_host,
+DROP COLUMN spacex__is_mod;
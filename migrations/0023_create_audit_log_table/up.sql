@@ -0,0 +1,17 @@
+CREATE TABLE audit_log (
+  id SERIAL PRIMARY KEY NOT NULL,
+  actor_user_id INTEGER
+    REFERENCES "user"
+    ON DELETE SET NULL,
+  entity_type VARCHAR(32) NOT NULL,
+  entity_id INTEGER NOT NULL,
+  thread_id INTEGER,
+  action VARCHAR(16) NOT NULL,
+  before JSONB,
+  after JSONB,
+  created_at_utc BIGINT NOT NULL
+);
+
+CREATE INDEX audit_log_thread_id ON audit_log (thread_id);
+CREATE INDEX audit_log_actor_user_id ON audit_log (actor_user_id);
+CREATE INDEX audit_log_created_at_utc ON audit_log (created_at_utc);
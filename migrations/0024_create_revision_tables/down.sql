@@ -0,0 +1,2 @@
+DROP TABLE event_revision;
+DROP TABLE section_revision;
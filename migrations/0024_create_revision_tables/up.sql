@@ -0,0 +1,36 @@
+CREATE TABLE section_revision (
+  id SERIAL PRIMARY KEY NOT NULL,
+  section_id INTEGER NOT NULL
+    REFERENCES section
+    ON DELETE CASCADE,
+  name VARCHAR(255) NOT NULL,
+  content TEXT NOT NULL,
+  created_by_user_id INTEGER
+    REFERENCES "user"
+    ON DELETE SET NULL,
+  created_at_utc BIGINT NOT NULL
+);
+CREATE INDEX section_revision_section_id ON section_revision (section_id);
+
+CREATE TABLE event_revision (
+  id SERIAL PRIMARY KEY NOT NULL,
+  event_id INTEGER NOT NULL
+    REFERENCES event
+    ON DELETE CASCADE,
+  posted BOOLEAN NOT NULL,
+  cols JSONB NOT NULL,
+  created_by_user_id INTEGER
+    REFERENCES "user"
+    ON DELETE SET NULL,
+  created_at_utc BIGINT NOT NULL
+);
+CREATE INDEX event_revision_event_id ON event_revision (event_id);
+
+-- Existing content is the first revision of each entity.
+INSERT INTO section_revision (section_id, name, content, created_at_utc)
+SELECT id, name, content, EXTRACT(EPOCH FROM NOW())::BIGINT
+FROM section;
+
+INSERT INTO event_revision (event_id, posted, cols, created_at_utc)
+SELECT id, posted, cols, EXTRACT(EPOCH FROM NOW())::BIGINT
+FROM event;
@@ -0,0 +1,26 @@
+-- Deleted rows are removed entirely,
+-- along with anything belonging to a deleted thread.
+DELETE FROM event
+WHERE deleted_at_utc IS NOT NULL
+  OR in_thread_id IN (SELECT id FROM thread WHERE deleted_at_utc IS NOT NULL);
+
+DELETE FROM section
+WHERE deleted_at_utc IS NOT NULL
+  OR in_thread_id IN (SELECT id FROM thread WHERE deleted_at_utc IS NOT NULL);
+
+DELETE FROM comment
+WHERE in_thread_id IN (SELECT id FROM thread WHERE deleted_at_utc IS NOT NULL);
+
+DELETE FROM thread
+WHERE deleted_at_utc IS NOT NULL;
+
+ALTER TABLE event
+DROP COLUMN deleted_at_utc,
+DROP COLUMN deleted_position;
+
+ALTER TABLE section
+DROP COLUMN deleted_at_utc,
+DROP COLUMN deleted_position;
+
+ALTER TABLE thread
+DROP COLUMN deleted_at_utc;
@@ -0,0 +1,13 @@
+ALTER TABLE thread
+ADD COLUMN deleted_at_utc BIGINT;
+
+-- The position is the index in the thread's `sections_id` or `events_id`
+-- at the time of deletion,
+-- allowing the entity to be restored to the same place.
+ALTER TABLE section
+ADD COLUMN deleted_at_utc BIGINT,
+ADD COLUMN deleted_position INTEGER;
+
+ALTER TABLE event
+ADD COLUMN deleted_at_utc BIGINT,
+ADD COLUMN deleted_position INTEGER;
@@ -0,0 +1,8 @@
+ALTER TABLE thread
+DROP COLUMN version;
+
+ALTER TABLE section
+DROP COLUMN version;
+
+ALTER TABLE event
+DROP COLUMN version;
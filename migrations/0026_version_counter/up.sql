@@ -0,0 +1,16 @@
+-- Incremented on every update,
+-- allowing clients to detect changes made since they last read the entity.
+ALTER TABLE thread
+ADD COLUMN version INTEGER
+NOT NULL
+DEFAULT 1;
+
+ALTER TABLE section
+ADD COLUMN version INTEGER
+NOT NULL
+DEFAULT 1;
+
+ALTER TABLE event
+ADD COLUMN version INTEGER
+NOT NULL
+DEFAULT 1;
@@ -0,0 +1,2 @@
+ALTER TABLE section
+DROP COLUMN lock_expires_at_utc;
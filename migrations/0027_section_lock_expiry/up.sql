@@ -0,0 +1,9 @@
+-- When the current lock may be taken by another user,
+-- or null if no lock is held.
+ALTER TABLE section
+ADD COLUMN lock_expires_at_utc BIGINT;
+
+-- Locks were previously guaranteed for ten minutes.
+UPDATE section
+SET lock_expires_at_utc = lock_assigned_at_utc + 600
+WHERE lock_held_by_user_id IS NOT NULL;
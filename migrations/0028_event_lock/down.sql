@@ -0,0 +1,4 @@
+ALTER TABLE event
+DROP COLUMN lock_held_by_user_id,
+DROP COLUMN lock_assigned_at_utc,
+DROP COLUMN lock_expires_at_utc;
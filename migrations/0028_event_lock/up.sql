@@ -0,0 +1,7 @@
+-- Events may be locked in the same manner as sections.
+ALTER TABLE event
+ADD COLUMN lock_held_by_user_id INTEGER
+CONSTRAINT event_lock_held_by_id REFERENCES "user"
+ON DELETE RESTRICT,
+ADD COLUMN lock_assigned_at_utc BIGINT NOT NULL DEFAULT 0,
+ADD COLUMN lock_expires_at_utc BIGINT;
@@ -0,0 +1,4 @@
+-- Set when the user's token is missing a scope we now request,
+-- so that they're sent back through Reddit the next time they log in.
+ALTER TABLE "user"
+ADD COLUMN needs_reauth BOOLEAN NOT NULL DEFAULT false;
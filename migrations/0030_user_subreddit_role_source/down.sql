@@ -0,0 +1,2 @@
+ALTER TABLE user_subreddit_role
+DROP COLUMN is_from_reddit;
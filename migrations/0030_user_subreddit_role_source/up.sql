@@ -0,0 +1,16 @@
+-- Roles synced from Reddit are managed automatically,
+-- while those granted by global admins must be left alone.
+ALTER TABLE user_subreddit_role
+ADD COLUMN is_from_reddit BOOLEAN NOT NULL DEFAULT false;
+
+-- Roles created by the sync weren't attributed to any user.
+UPDATE user_subreddit_role
+SET is_from_reddit = true
+WHERE role = 'moderator'
+AND id IN (
+  SELECT entity_id
+  FROM audit_log
+  WHERE entity_type = 'user_subreddit_role'
+  AND action = 'create'
+  AND actor_user_id IS NULL
+);
@@ -0,0 +1,25 @@
+use derive_more::Display;
+
+/// How a post or comment should be distinguished.
+///
+/// Descriptions are from Reddit.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Distinguish {
+    /// Distinguish the thing as a moderator.
+    #[display(fmt = "yes")]
+    Moderator,
+
+    /// Remove any distinguishing.
+    #[display(fmt = "no")]
+    None,
+
+    /// Distinguish the thing as an admin.
+    /// Only available to Reddit admins.
+    #[display(fmt = "admin")]
+    Admin,
+
+    /// Distinguish the thing with a special flag.
+    /// Only available to Reddit admins.
+    #[display(fmt = "special")]
+    Special,
+}
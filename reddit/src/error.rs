@@ -0,0 +1,141 @@
+use itertools::Itertools;
+use reqwest::{StatusCode, UrlError};
+use std::{error::Error as StdError, fmt, time::Duration};
+
+/// A convenience type for any result that may contain an `Error`.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A single error returned by Reddit in the `json.errors` field of a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    /// The machine-readable code, such as `RATELIMIT` or `SUBREDDIT_NOEXIST`.
+    pub code:    String,
+    /// A human-readable explanation of the error.
+    pub message: String,
+    /// The form field the error relates to, if any.
+    pub field:   Option<String>,
+}
+
+impl fmt::Display for ApiError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+/// Any error that can occur when communicating with Reddit.
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or the response could not be read.
+    Transport(reqwest::Error),
+    /// Reddit responded with an unsuccessful HTTP status.
+    Status(StatusCode),
+    /// Reddit accepted the request,
+    /// but reported one or more errors in the `json.errors` field.
+    Api(Vec<ApiError>),
+    /// Reddit asked us to wait before making further requests,
+    /// either via a `RATELIMIT` error or by exhausting the request budget.
+    /// Contains the time remaining until another request may be made.
+    RateLimited(Duration),
+    /// A new access token could not be obtained using the refresh token.
+    TokenRefresh(Box<Error>),
+    /// The user didn't grant a scope necessary for the request.
+    /// They must authenticate again for it to succeed.
+    InsufficientScope,
+    /// The response did not have the expected structure.
+    MalformedResponse(String),
+    /// A URL could not be constructed from the provided values.
+    Url(UrlError),
+}
+
+impl Error {
+    /// Did Reddit report an error with the provided code?
+    #[inline]
+    pub fn has_api_error(&self, code: &str) -> bool {
+        match self {
+            Error::Api(errors) => errors.iter().any(|error| error.code == code),
+            _ => false,
+        }
+    }
+
+    /// Was the request rejected before Reddit acted on it,
+    /// so that retrying it cannot repeat its effect?
+    /// This includes rate limiting, the service being unavailable,
+    /// and failing to obtain an access token before the request was sent.
+    #[inline]
+    pub fn is_rejected(&self) -> bool {
+        match self {
+            Error::Status(status) => {
+                *status == StatusCode::TOO_MANY_REQUESTS
+                    || *status == StatusCode::SERVICE_UNAVAILABLE
+            }
+            Error::RateLimited(_) => true,
+            Error::TokenRefresh(err) => err.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// Is the error likely to resolve itself if the request is retried?
+    /// This includes timeouts, connection failures,
+    /// server errors, and rate limiting.
+    #[inline]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Transport(err) => err.is_timeout() || err.is_http(),
+            Error::Status(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::RateLimited(_) => true,
+            Error::TokenRefresh(err) => err.is_transient(),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match self {
+            Transport(err) => write!(f, "error communicating with Reddit: {}", err),
+            Status(status) => write!(f, "Reddit responded with status {}", status),
+            Api(errors) => write!(f, "Reddit returned errors: {}", errors.iter().join(", ")),
+            RateLimited(wait) => write!(
+                f,
+                "rate limited by Reddit; try again in {} seconds",
+                wait.as_secs()
+            ),
+            TokenRefresh(err) => write!(f, "unable to refresh access token: {}", err),
+            InsufficientScope => write!(f, "the user has not granted the necessary scope"),
+            MalformedResponse(reason) => write!(f, "malformed response from Reddit: {}", reason),
+            Url(err) => write!(f, "invalid URL: {}", err),
+        }
+    }
+}
+
+impl StdError for Error {
+    #[inline]
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        use Error::*;
+        match self {
+            Transport(err) => Some(err),
+            TokenRefresh(err) => Some(&**err),
+            Url(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    #[inline]
+    fn from(err: reqwest::Error) -> Self {
+        Error::Transport(err)
+    }
+}
+
+impl From<UrlError> for Error {
+    #[inline]
+    fn from(err: UrlError) -> Self {
+        Error::Url(err)
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// A link flair that may be applied to submissions in a subreddit.
+///
+/// Only the fields relevant to selecting a flair are retained.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkFlair {
+    /// The ID of the flair template.
+    pub id: String,
+    /// The text displayed by default.
+    pub text: String,
+    /// Whether the text can be changed when the flair is selected.
+    #[serde(default)]
+    pub text_editable: bool,
+    /// Whether the flair may only be selected by moderators.
+    #[serde(default)]
+    pub mod_only: bool,
+}
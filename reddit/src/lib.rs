@@ -2,20 +2,64 @@
 #![warn(clippy::nursery)] // Don't deny, as there may be unknown bugs.
 #![allow(intra_doc_link_resolution_failure, clippy::match_bool)]
 
+mod distinguish;
+mod error;
+mod flair;
+mod ratelimit;
 mod scope;
 
 use derive_builder::*;
+pub use distinguish::Distinguish;
+pub use error::{ApiError, Error, Result};
+pub use flair::LinkFlair;
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use reqwest::{header::USER_AGENT, Client, Url, UrlError};
+pub use ratelimit::RateLimit;
+use reqwest::{
+    header::{USER_AGENT, WWW_AUTHENTICATE},
+    Client,
+    Method,
+    Response,
+    StatusCode,
+    Url,
+};
 pub use scope::Scope;
-use serde::Deserialize;
-use std::time::{Duration, SystemTime};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value as Json;
+use std::{
+    thread,
+    time::{Duration, SystemTime},
+};
 
 lazy_static! {
     static ref CLIENT: Client = Client::builder().gzip(true).build().unwrap();
 }
 
+/// The base URL used for authorization and obtaining tokens,
+/// unless otherwise specified when building a `Reddit` instance.
+pub const DEFAULT_AUTH_BASE_URL: &str = "https://ssl.reddit.com";
+
+/// The base URL used for all authenticated requests,
+/// unless otherwise specified when building a `Reddit` instance.
+pub const DEFAULT_API_BASE_URL: &str = "https://oauth.reddit.com";
+
+/// The maximum number of characters Reddit permits in the body of a self post.
+pub const MAX_SELFTEXT_LENGTH: usize = 40_000;
+
+/// The maximum number of characters Reddit permits in the body of a comment.
+pub const MAX_COMMENT_LENGTH: usize = 10_000;
+
+/// How many times a transient failure is retried,
+/// unless otherwise specified when building a `Reddit` instance.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// The longest we are willing to wait before a single retry,
+/// unless otherwise specified when building a `Reddit` instance.
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// The delay before the first retry, which doubles with each subsequent attempt.
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
 /// Returns a globally unique identifier.
 /// Specifically, v4, which is not based on any input factors.
 #[inline]
@@ -25,14 +69,33 @@ pub fn guid() -> String {
 
 /// A Reddit configuration.
 /// Used to create `User`s.
+///
+/// The base URLs and HTTP client default to those of Reddit itself,
+/// but can be overridden to point at any compatible server (such as a local mock).
+///
+/// Requests that fail transiently (timeouts, server errors, and rate limiting)
+/// are retried up to `max_retries` times,
+/// waiting no longer than `max_wait` before any one attempt.
+/// Submitting a post or comment is only retried if Reddit rejected it outright,
+/// as a request that timed out may have been processed regardless.
 #[derive(Builder, Debug)]
 pub struct Reddit<'a> {
-    redirect_uri: &'a str,
-    user_agent:   &'a str,
-    client_id:    &'a str,
-    secret:       &'a str,
-    permanent:    bool,
-    scopes:       &'a [Scope],
+    redirect_uri:  &'a str,
+    user_agent:    &'a str,
+    client_id:     &'a str,
+    secret:        &'a str,
+    permanent:     bool,
+    scopes:        &'a [Scope],
+    #[builder(default = "DEFAULT_AUTH_BASE_URL")]
+    auth_base_url: &'a str,
+    #[builder(default = "DEFAULT_API_BASE_URL")]
+    api_base_url:  &'a str,
+    #[builder(default = "CLIENT.clone()")]
+    client:        Client,
+    #[builder(default = "DEFAULT_MAX_RETRIES")]
+    max_retries:   u32,
+    #[builder(default = "DEFAULT_MAX_WAIT")]
+    max_wait:      Duration,
 }
 
 /// A Reddit User,
@@ -89,6 +152,48 @@ impl Reddit<'_> {
     pub const fn scopes(&self) -> &[Scope] {
         self.scopes
     }
+
+    /// The base URL used for authorization and obtaining tokens.
+    #[inline(always)]
+    pub const fn auth_base_url(&self) -> &str {
+        self.auth_base_url
+    }
+
+    /// The base URL used for all authenticated requests.
+    #[inline(always)]
+    pub const fn api_base_url(&self) -> &str {
+        self.api_base_url
+    }
+
+    /// The HTTP client used to perform all requests.
+    #[inline(always)]
+    pub const fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// How many times a transient failure is retried.
+    #[inline(always)]
+    pub const fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// The longest we are willing to wait before a single retry.
+    #[inline(always)]
+    pub const fn max_wait(&self) -> Duration {
+        self.max_wait
+    }
+
+    /// Given the path of an authorization endpoint, return the full URL.
+    #[inline(always)]
+    fn auth_endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.auth_base_url, path)
+    }
+
+    /// Given the endpoint's path, return the full URL.
+    #[inline(always)]
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.api_base_url, path)
+    }
 }
 
 // Getters
@@ -109,7 +214,7 @@ impl User<'_> {
     /// Get the access token of the user,
     /// fetching a new one from Reddit if necessary.
     #[inline]
-    pub fn access_token(&mut self) -> &String {
+    pub fn access_token(&mut self) -> Result<&String> {
         // Refresh the access token if it's expired.
         if self.expires_at < SystemTime::now() {
             #[derive(Deserialize)]
@@ -118,8 +223,10 @@ impl User<'_> {
                 expires_in:   u64,
             }
 
-            let response: APIReturnType = CLIENT
-                .post("https://ssl.reddit.com/api/v1/access_token")
+            let response: APIReturnType = self
+                .reddit_instance
+                .client
+                .post(&self.reddit_instance.auth_endpoint("/api/v1/access_token"))
                 .basic_auth(
                     self.reddit_instance.client_id,
                     Some(self.reddit_instance.secret),
@@ -129,15 +236,15 @@ impl User<'_> {
                     ("refresh_token", &self.refresh_token),
                 ])
                 .send()
-                .unwrap()
-                .json()
-                .unwrap();
+                .map_err(Error::from)
+                .and_then(decode)
+                .map_err(|err| Error::TokenRefresh(Box::new(err)))?;
 
             self.access_token = response.access_token;
             self.expires_at = SystemTime::now() + Duration::from_secs(response.expires_in);
         }
 
-        &self.access_token
+        Ok(&self.access_token)
     }
 
     /// When the access token is due to expire.
@@ -145,14 +252,21 @@ impl User<'_> {
     pub const fn expires_at(&self) -> &SystemTime {
         &self.expires_at
     }
+
+    /// The user's request budget, as last reported by Reddit.
+    /// Returns `None` if no request has been made on behalf of the user yet.
+    #[inline]
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        ratelimit::get(&self.refresh_token)
+    }
 }
 
 impl<'a> Reddit<'a> {
     /// Get a URL to send the user to for authentication.
     #[inline]
-    pub fn get_auth_url(&self, callback: &str) -> Result<String, UrlError> {
+    pub fn get_auth_url(&self, callback: &str) -> Result<String> {
         Ok(Url::parse_with_params(
-            "https://ssl.reddit.com/api/v1/authorize",
+            &self.auth_endpoint("/api/v1/authorize"),
             &[
                 ("response_type", "code"),
                 ("client_id", self.client_id),
@@ -173,7 +287,7 @@ impl<'a> Reddit<'a> {
 
     /// Given a code, obtain a refresh token from Reddit.
     #[inline]
-    pub fn obtain_refresh_token(&self, code: &str) -> reqwest::Result<User<'_>> {
+    pub fn obtain_refresh_token(&self, code: &str) -> Result<User<'_>> {
         #[derive(Deserialize, Debug)]
         struct APIReturnType {
             access_token:  String,
@@ -181,64 +295,212 @@ impl<'a> Reddit<'a> {
             refresh_token: Option<String>,
         }
 
-        let data: APIReturnType = CLIENT
-            .post("https://ssl.reddit.com/api/v1/access_token")
-            .basic_auth(self.client_id, Some(self.secret))
-            .form(&[
-                ("grant_type", "authorization_code"),
-                ("code", code),
-                ("redirect_uri", self.redirect_uri),
-            ])
-            .send()?
-            .json()?;
+        let data: APIReturnType = decode(
+            self.client
+                .post(&self.auth_endpoint("/api/v1/access_token"))
+                .basic_auth(self.client_id, Some(self.secret))
+                .form(&[
+                    ("grant_type", "authorization_code"),
+                    ("code", code),
+                    ("redirect_uri", self.redirect_uri),
+                ])
+                .send()?,
+        )?;
 
         Ok(User {
             reddit_instance: self,
-            refresh_token:   data.refresh_token.unwrap(),
+            refresh_token:   data
+                .refresh_token
+                .ok_or_else(|| Error::MalformedResponse("no refresh token was provided".into()))?,
             access_token:    data.access_token,
             expires_at:      SystemTime::now() + Duration::from_secs(data.expires_in),
         })
     }
 }
 
-/// Given the endpoint's path, return the full URL.
-#[inline(always)]
-fn endpoint(path: &str) -> String {
-    format!("https://oauth.reddit.com{}", path)
+/// Ensure a response has a successful status code.
+///
+/// Reddit indicates that the access token wasn't granted a necessary scope
+/// via the `WWW-Authenticate` header of a `403 Forbidden` response.
+#[inline]
+fn check_status(response: Response) -> Result<Response> {
+    let is_insufficient_scope = || {
+        response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, |value| value.contains("insufficient_scope"))
+    };
+
+    if response.status().is_success() {
+        Ok(response)
+    } else if response.status() == StatusCode::FORBIDDEN && is_insufficient_scope() {
+        Err(Error::InsufficientScope)
+    } else {
+        Err(Error::Status(response.status()))
+    }
+}
+
+/// Deserialize the body of a response,
+/// provided it has a successful status code.
+#[inline]
+fn decode<T: DeserializeOwned>(response: Response) -> Result<T> {
+    check_status(response)?
+        .json()
+        .map_err(|err| Error::MalformedResponse(err.to_string()))
+}
+
+/// Deserialize the body of a response to a request made with `api_type=json`.
+/// Any errors Reddit reports in the `json.errors` field are returned as an `Error::Api`.
+#[inline]
+fn decode_api_json(response: Response) -> Result<Json> {
+    let body: Json = decode(response)?;
+
+    let errors: Vec<_> = match body["json"]["errors"].as_array() {
+        Some(errors) => errors
+            .iter()
+            .map(|error| ApiError {
+                code:    error[0].as_str().unwrap_or_default().to_owned(),
+                message: error[1].as_str().unwrap_or_default().to_owned(),
+                field:   error[2].as_str().map(str::to_owned),
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    if errors.is_empty() {
+        return Ok(body);
+    }
+
+    // Reddit tells us how long to wait (in seconds) when we're posting too quickly.
+    match body["json"]["ratelimit"].as_f64() {
+        Some(wait) if errors.iter().any(|error| error.code == "RATELIMIT") => Err(
+            Error::RateLimited(Duration::from_millis((wait * 1000.) as u64)),
+        ),
+        _ => Err(Error::Api(errors)),
+    }
 }
 
 /// Endpoints
 impl User<'_> {
+    /// Perform an idempotent request against the API,
+    /// retrying transient failures with exponential backoff.
+    ///
+    /// If the user's request budget has been exhausted,
+    /// the request is delayed until it is replenished.
+    /// No single delay will exceed the instance's `max_wait`;
+    /// if a longer delay would be necessary, the error is returned instead.
     #[inline]
-    fn me(&mut self) -> reqwest::Result<reqwest::Response> {
-        CLIENT
-            .get(&endpoint("/api/v1/me"))
-            .header(USER_AGENT, self.reddit_instance.user_agent)
-            .bearer_auth(self.access_token())
-            .send()
+    fn call<T>(
+        &mut self,
+        method: Method,
+        path: &str,
+        params: &[(&str, &str)],
+        parse: fn(Response) -> Result<T>,
+    ) -> Result<T> {
+        self.call_retrying(method, path, params, parse, Error::is_transient)
     }
 
+    /// Perform a request that must not be repeated,
+    /// such as one creating a post or comment.
+    ///
+    /// A request that timed out may still have been processed by Reddit,
+    /// so the request is only retried if it was rejected outright.
     #[inline]
-    fn prefs(&mut self) -> reqwest::Result<reqwest::Response> {
-        CLIENT
-            .get(&endpoint("/api/v1/me/prefs"))
-            .header(USER_AGENT, self.reddit_instance.user_agent)
-            .bearer_auth(self.access_token())
-            .send()
+    fn call_once<T>(
+        &mut self,
+        method: Method,
+        path: &str,
+        params: &[(&str, &str)],
+        parse: fn(Response) -> Result<T>,
+    ) -> Result<T> {
+        self.call_retrying(method, path, params, parse, Error::is_rejected)
     }
 
+    /// Perform a request against the API,
+    /// retrying failures for which `should_retry` returns `true`.
     #[inline]
-    fn submit(
+    fn call_retrying<T>(
         &mut self,
-        subreddit: &str,
-        title: &str,
-        text: Option<&str>,
-    ) -> reqwest::Result<reqwest::Response> {
-        CLIENT
-            .post(&endpoint("/api/submit"))
-            .header(USER_AGENT, self.reddit_instance.user_agent)
-            .bearer_auth(self.access_token())
-            .form(&[
+        method: Method,
+        path: &str,
+        params: &[(&str, &str)],
+        parse: fn(Response) -> Result<T>,
+        should_retry: fn(&Error) -> bool,
+    ) -> Result<T> {
+        let mut attempt = 0;
+
+        loop {
+            let result = self.wait_for_budget().and_then(|_| {
+                let request = self
+                    .reddit_instance
+                    .client
+                    .request(method.clone(), &self.reddit_instance.endpoint(path))
+                    .header(USER_AGENT, self.reddit_instance.user_agent)
+                    .bearer_auth(self.access_token()?);
+                let request = match method {
+                    Method::GET => request.query(params),
+                    _ => request.form(params),
+                };
+
+                ratelimit::consume(&self.refresh_token);
+                let response = request.send()?;
+                ratelimit::update(&self.refresh_token, response.headers());
+                parse(response)
+            });
+
+            let err = match result {
+                Err(ref err) if should_retry(err) => err,
+                result => return result,
+            };
+
+            let delay = match err {
+                Error::RateLimited(delay) => *delay,
+                _ => BASE_BACKOFF * 2_u32.pow(attempt),
+            };
+            if attempt >= self.reddit_instance.max_retries || delay > self.reddit_instance.max_wait
+            {
+                return result;
+            }
+
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    /// If the user's request budget has been exhausted,
+    /// wait until it is replenished.
+    #[inline]
+    fn wait_for_budget(&self) -> Result<()> {
+        match self.rate_limit() {
+            Some(budget) if budget.is_exhausted() => {
+                let delay = budget.time_until_reset();
+                if delay > self.reddit_instance.max_wait {
+                    return Err(Error::RateLimited(delay));
+                }
+                thread::sleep(delay);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn me<T: DeserializeOwned>(&mut self) -> Result<T> {
+        self.call(Method::GET, "/api/v1/me", &[], decode)
+    }
+
+    #[inline]
+    fn prefs<T: DeserializeOwned>(&mut self) -> Result<T> {
+        self.call(Method::GET, "/api/v1/me/prefs", &[], decode)
+    }
+
+    #[inline]
+    fn submit(&mut self, subreddit: &str, title: &str, text: Option<&str>) -> Result<Json> {
+        self.call_once(
+            Method::POST,
+            "/api/submit",
+            &[
                 ("kind", "self"),
                 ("api_type", "json"),
                 ("extensions", "json"),
@@ -246,46 +508,136 @@ impl User<'_> {
                 ("sr", subreddit),
                 ("title", title),
                 ("text", text.unwrap_or_default()),
-            ])
-            .send()
+            ],
+            decode_api_json,
+        )
+    }
+
+    #[inline]
+    fn edit(&mut self, thing_id: &str, text: &str) -> Result<Json> {
+        self.call(
+            Method::POST,
+            "/api/editusertext",
+            &[("api_type", "json"), ("thing_id", thing_id), ("text", text)],
+            decode_api_json,
+        )
+    }
+
+    #[inline]
+    fn approve_internal(&mut self, thing_id: &str) -> Result<Response> {
+        self.call(
+            Method::POST,
+            "/api/approve",
+            &[("id", thing_id)],
+            check_status,
+        )
     }
 
     #[inline]
-    fn edit(&mut self, thing_id: &str, text: &str) -> reqwest::Result<reqwest::Response> {
-        CLIENT
-            .post(&endpoint("/api/editusertext"))
-            .header(USER_AGENT, self.reddit_instance.user_agent)
-            .bearer_auth(self.access_token())
-            .form(&[("api_type", "json"), ("thing_id", thing_id), ("text", text)])
-            .send()
+    fn set_sticky_internal(&mut self, thing_id: &str, state: bool) -> Result<Json> {
+        self.call(
+            Method::POST,
+            "/api/set_subreddit_sticky",
+            &[
+                ("api_type", "json"),
+                ("id", thing_id),
+                ("state", &state.to_string()),
+            ],
+            decode_api_json,
+        )
     }
 
     #[inline]
-    fn approve_internal(&mut self, thing_id: &str) -> reqwest::Result<reqwest::Response> {
-        CLIENT
-            .post(&endpoint("/api/approve"))
-            .header(USER_AGENT, self.reddit_instance.user_agent)
-            .bearer_auth(self.access_token())
-            .form(&[("id", thing_id)])
-            .send()
+    fn comment(&mut self, parent_thing_id: &str, text: &str) -> Result<Json> {
+        self.call_once(
+            Method::POST,
+            "/api/comment",
+            &[
+                ("api_type", "json"),
+                ("thing_id", parent_thing_id),
+                ("text", text),
+            ],
+            decode_api_json,
+        )
     }
 
     #[inline]
-    fn set_sticky_internal(
+    fn delete_internal(&mut self, thing_id: &str) -> Result<Response> {
+        self.call(Method::POST, "/api/del", &[("id", thing_id)], check_status)
+    }
+
+    #[inline]
+    fn distinguish_internal(
         &mut self,
         thing_id: &str,
-        state: bool,
-    ) -> reqwest::Result<reqwest::Response> {
-        CLIENT
-            .post(&endpoint("/api/set_subreddit_sticky"))
-            .header(USER_AGENT, self.reddit_instance.user_agent)
-            .bearer_auth(self.access_token())
-            .form(&[
+        how: Distinguish,
+        sticky: bool,
+    ) -> Result<Json> {
+        self.call(
+            Method::POST,
+            "/api/distinguish",
+            &[
                 ("api_type", "json"),
                 ("id", thing_id),
-                ("state", &state.to_string()),
-            ])
-            .send()
+                ("how", &how.to_string()),
+                ("sticky", &sticky.to_string()),
+            ],
+            decode_api_json,
+        )
+    }
+
+    #[inline]
+    fn mine_moderator(&mut self, after: Option<&str>) -> Result<Json> {
+        self.call(
+            Method::GET,
+            "/subreddits/mine/moderator",
+            &[("limit", "100"), ("after", after.unwrap_or_default())],
+            decode,
+        )
+    }
+
+    #[inline]
+    fn about_moderators(&mut self, subreddit: &str) -> Result<Json> {
+        self.call(
+            Method::GET,
+            &format!("/r/{}/about/moderators", subreddit),
+            &[],
+            decode,
+        )
+    }
+
+    #[inline]
+    fn link_flair(&mut self, subreddit: &str) -> Result<Vec<LinkFlair>> {
+        self.call(
+            Method::GET,
+            &format!("/r/{}/api/link_flair_v2", subreddit),
+            &[],
+            decode,
+        )
+    }
+
+    #[inline]
+    fn select_flair(
+        &mut self,
+        subreddit: &str,
+        thing_id: &str,
+        flair_template_id: Option<&str>,
+        text: Option<&str>,
+    ) -> Result<Json> {
+        let mut params = vec![("api_type", "json"), ("link", thing_id)];
+        if let Some(flair_template_id) = flair_template_id {
+            params.push(("flair_template_id", flair_template_id));
+        }
+        if let Some(text) = text {
+            params.push(("text", text));
+        }
+
+        self.call(
+            Method::POST,
+            &format!("/r/{}/api/selectflair", subreddit),
+            &params,
+            decode_api_json,
+        )
     }
 }
 
@@ -293,29 +645,77 @@ impl User<'_> {
 impl User<'_> {
     /// Get a user's username on Reddit.
     #[inline]
-    pub fn username(&mut self) -> reqwest::Result<String> {
-        // We may use the `is_mod` field in the future
-        // to automatically determine if the user is a moderator
-        // of a specific subreddit
-        #[allow(unused)]
+    pub fn username(&mut self) -> Result<String> {
+        #[derive(Deserialize, Debug)]
+        struct APIReturnType {
+            name: String,
+        }
+
+        Ok(self.me::<APIReturnType>()?.name)
+    }
+
+    /// Check if the user is a moderator of any subreddit.
+    #[inline]
+    pub fn is_moderator(&mut self) -> Result<bool> {
         #[derive(Deserialize, Debug)]
         struct APIReturnType {
-            name:   String,
             is_mod: bool,
         }
 
-        Ok(self.me()?.json::<APIReturnType>()?.name)
+        Ok(self.me::<APIReturnType>()?.is_mod)
+    }
+
+    /// Get the names of all subreddits the user moderates.
+    #[inline]
+    pub fn moderated_subreddits(&mut self) -> Result<Vec<String>> {
+        let mut subreddits = vec![];
+        let mut after = None;
+
+        // The listing is paginated, so keep requesting pages until there are none left.
+        loop {
+            let listing = self.mine_moderator(after.as_ref().map(String::as_str))?;
+            let children = listing["data"]["children"]
+                .as_array()
+                .ok_or_else(|| Error::MalformedResponse("no subreddits were returned".into()))?;
+
+            subreddits.extend(
+                children
+                    .iter()
+                    .filter_map(|child| child["data"]["display_name"].as_str())
+                    .map(str::to_owned),
+            );
+
+            match listing["data"]["after"].as_str() {
+                Some(next) => after = Some(next.to_owned()),
+                None => return Ok(subreddits),
+            }
+        }
+    }
+
+    /// Get the usernames of all moderators of a subreddit.
+    #[inline]
+    pub fn moderators_of(&mut self, subreddit: &str) -> Result<Vec<String>> {
+        let children = self.about_moderators(subreddit)?["data"]["children"]
+            .as_array()
+            .cloned()
+            .ok_or_else(|| Error::MalformedResponse("no moderators were returned".into()))?;
+
+        Ok(children
+            .iter()
+            .filter_map(|child| child["name"].as_str())
+            .map(str::to_owned)
+            .collect())
     }
 
     /// Get a user's language preference.
     #[inline]
-    pub fn lang(&mut self) -> reqwest::Result<String> {
+    pub fn lang(&mut self) -> Result<String> {
         #[derive(Deserialize, Debug)]
         struct APIReturnType {
             lang: String,
         }
 
-        Ok(self.prefs()?.json::<APIReturnType>()?.lang)
+        Ok(self.prefs::<APIReturnType>()?.lang)
     }
 
     /// Submit a post on Reddit with a given title and body.
@@ -326,35 +726,81 @@ impl User<'_> {
         subreddit: &str,
         title: &str,
         text: Option<&str>,
-    ) -> reqwest::Result<String> {
-        Ok(self
-            .submit(subreddit, title, text)?
-            .json::<serde_json::Value>()
-            .unwrap()
-            .get("json")
-            .unwrap()
-            .get("data")
-            .unwrap()
-            .get("id")
-            .unwrap()
-            .to_string())
+    ) -> Result<String> {
+        self.submit(subreddit, title, text)?["json"]["data"]["id"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| Error::MalformedResponse("no id was returned for the post".into()))
     }
 
     /// Edit an existing thread on Reddit.
     #[inline]
-    pub fn edit_self_post(&mut self, thing_id: &str, text: &str) -> reqwest::Result<()> {
+    pub fn edit_self_post(&mut self, thing_id: &str, text: &str) -> Result<()> {
         self.edit(thing_id, text).map(|_| ())
     }
 
     /// Approve a thread on Reddit.
     #[inline]
-    pub fn approve(&mut self, thing_id: &str) -> reqwest::Result<()> {
+    pub fn approve(&mut self, thing_id: &str) -> Result<()> {
         self.approve_internal(thing_id).map(|_| ())
     }
 
     /// Sticky or unsticky a thread on Reddit.
     #[inline]
-    pub fn set_sticky(&mut self, thing_id: &str, state: bool) -> reqwest::Result<()> {
+    pub fn set_sticky(&mut self, thing_id: &str, state: bool) -> Result<()> {
         self.set_sticky_internal(thing_id, state).map(|_| ())
     }
+
+    /// Submit a comment on Reddit in reply to a post or another comment.
+    /// Returns the `thing_id` of the new comment.
+    #[inline]
+    pub fn submit_comment(&mut self, parent_thing_id: &str, text: &str) -> Result<String> {
+        self.comment(parent_thing_id, text)?["json"]["data"]["things"][0]["data"]["id"]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| Error::MalformedResponse("no id was returned for the comment".into()))
+    }
+
+    /// Edit an existing comment on Reddit.
+    #[inline]
+    pub fn edit_comment(&mut self, thing_id: &str, text: &str) -> Result<()> {
+        self.edit(thing_id, text).map(|_| ())
+    }
+
+    /// Delete a post or comment on Reddit.
+    #[inline]
+    pub fn delete(&mut self, thing_id: &str) -> Result<()> {
+        self.delete_internal(thing_id).map(|_| ())
+    }
+
+    /// Distinguish a post or comment on Reddit.
+    ///
+    /// Top-level comments distinguished as a moderator can also be stickied,
+    /// placing them at the top of the comments.
+    #[inline]
+    pub fn distinguish(&mut self, thing_id: &str, how: Distinguish, sticky: bool) -> Result<()> {
+        self.distinguish_internal(thing_id, how, sticky).map(|_| ())
+    }
+
+    /// Get the link flairs available for submissions in a subreddit.
+    #[inline]
+    pub fn link_flairs(&mut self, subreddit: &str) -> Result<Vec<LinkFlair>> {
+        self.link_flair(subreddit)
+    }
+
+    /// Set the flair of a submission on Reddit.
+    ///
+    /// If a template is provided, its text is used unless `text` is also provided.
+    /// Otherwise, `text` is applied on its own, which only moderators are permitted to do.
+    #[inline]
+    pub fn set_link_flair(
+        &mut self,
+        subreddit: &str,
+        thing_id: &str,
+        flair_template_id: Option<&str>,
+        text: Option<&str>,
+    ) -> Result<()> {
+        self.select_flair(subreddit, thing_id, flair_template_id, text)
+            .map(|_| ())
+    }
 }
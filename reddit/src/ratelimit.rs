@@ -0,0 +1,84 @@
+use lazy_static::lazy_static;
+use reqwest::header::HeaderMap;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+lazy_static! {
+    /// The most recently reported budget for each user, keyed by refresh token.
+    ///
+    /// Reddit tracks its limits per user (rather than per access token),
+    /// so the budget carries over when an access token is refreshed.
+    static ref BUDGETS: Mutex<HashMap<String, RateLimit>> = Mutex::new(HashMap::new());
+}
+
+/// The request budget Reddit has allotted to a user,
+/// as reported by the `X-Ratelimit-*` headers of the most recent response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// How many requests may be made before the period resets.
+    pub remaining: f64,
+    /// How many requests have been made in the current period.
+    pub used:      u64,
+    /// When the current period ends and the budget is replenished.
+    pub reset_at:  SystemTime,
+}
+
+impl RateLimit {
+    /// Parse the budget from the headers of a response, if present.
+    #[inline]
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| headers.get(name)?.to_str().ok();
+
+        Some(Self {
+            remaining: header("x-ratelimit-remaining")?.parse().ok()?,
+            used:      header("x-ratelimit-used")?.parse().ok()?,
+            reset_at:  SystemTime::now()
+                + Duration::from_secs(header("x-ratelimit-reset")?.parse().ok()?),
+        })
+    }
+
+    /// Has the budget been used up for the current period?
+    #[inline]
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining < 1. && self.reset_at > SystemTime::now()
+    }
+
+    /// How long until the budget is replenished.
+    #[inline]
+    pub fn time_until_reset(&self) -> Duration {
+        self.reset_at
+            .duration_since(SystemTime::now())
+            .unwrap_or_default()
+    }
+}
+
+/// Get the last known budget for the user with the provided refresh token.
+#[inline]
+pub(crate) fn get(refresh_token: &str) -> Option<RateLimit> {
+    BUDGETS.lock().unwrap().get(refresh_token).copied()
+}
+
+/// Record the budget reported by a response,
+/// if it contains the necessary headers.
+#[inline]
+pub(crate) fn update(refresh_token: &str, headers: &HeaderMap) {
+    if let Some(budget) = RateLimit::from_headers(headers) {
+        BUDGETS
+            .lock()
+            .unwrap()
+            .insert(refresh_token.to_owned(), budget);
+    }
+}
+
+/// Record a request as having been made,
+/// so concurrent callers see the reduced budget before the response arrives.
+#[inline]
+pub(crate) fn consume(refresh_token: &str) {
+    if let Some(budget) = BUDGETS.lock().unwrap().get_mut(refresh_token) {
+        budget.remaining -= 1.;
+        budget.used += 1;
+    }
+}
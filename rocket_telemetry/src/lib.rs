@@ -59,13 +59,19 @@ impl Fairing for Telemetry {
             return;
         }
 
-        let body_size = match response.body_bytes() {
-            Some(body) => {
-                let len = body.len();
-                response.set_sized_body(Cursor::new(body));
-                len
+        // Streamed bodies, such as server-sent events, may never end,
+        // so their size is not recorded.
+        let body_size = if response.body().map_or(false, |body| body.is_chunked()) {
+            0
+        } else {
+            match response.body_bytes() {
+                Some(body) => {
+                    let len = body.len();
+                    response.set_sized_body(Cursor::new(body));
+                    len
+                }
+                None => 0,
             }
-            None => 0,
         };
 
         REQUESTS.write().push(RequestLogEntry {